@@ -0,0 +1,58 @@
+//! Compares finding collisions in the random scene by brute force against using a BVH.
+//!
+//! Run with `cargo bench`.
+
+use std::time::{Duration, Instant};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use rez::{random_scene, Bvh, Collider, Ray, Vec3};
+
+const RAYS: usize = 100_000;
+
+fn rays() -> Vec<Ray> {
+    let mut rng = StdRng::seed_from_u64(0);
+    // Rays from around the default camera position towards the scene.
+    (0..RAYS)
+        .map(|_| {
+            let orig = Vec3::new(13.0, 2.0, 3.0) + Vec3::random_in_unit_sphere(&mut rng);
+            let target = Vec3::new(rng.gen_range(-11.0..11.0), 0.0, rng.gen_range(-11.0..11.0));
+            Ray::new(orig, target - orig)
+        })
+        .collect()
+}
+
+fn bench<C: Collider>(name: &str, scene: C, rays: &[Ray]) -> Duration {
+    let start = Instant::now();
+    let hits = rays
+        .iter()
+        .filter(|&&r| scene.collide(r, (0.001, f64::INFINITY)).is_some())
+        .count();
+    let elapsed = start.elapsed();
+    println!(
+        "{:<12} {:>10.2?} total, {:>8.0?} per ray ({} hits)",
+        name,
+        elapsed,
+        elapsed / rays.len() as u32,
+        hits
+    );
+    elapsed
+}
+
+fn main() {
+    let rays = rays();
+
+    let flat = random_scene(&mut StdRng::seed_from_u64(1));
+    println!("random scene: {} objects, {} rays", flat.len(), rays.len());
+    let brute = bench("brute force", &flat, &rays);
+
+    let start = Instant::now();
+    let bvh = Bvh::new(random_scene(&mut StdRng::seed_from_u64(1)));
+    println!("{:<12} {:>10.2?}", "bvh build", start.elapsed());
+    let accelerated = bench("bvh", &bvh, &rays);
+
+    println!(
+        "speedup      {:>10.1}x",
+        brute.as_secs_f64() / accelerated.as_secs_f64()
+    );
+}
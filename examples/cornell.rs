@@ -0,0 +1,46 @@
+//! Renders the Cornell box, lit only by the panel in its ceiling.
+//!
+//! Run with `cargo run --release --example cornell -- cornell.webp`.
+
+use std::{fs::File, io, sync::Arc};
+
+use rez::{cornell_box, encode_webp, Bvh, Camera, Colour, Raytracer, Vec3};
+
+fn main() -> io::Result<()> {
+    let args = std::env::args().collect::<Vec<_>>();
+    let (stdout, mut lock, mut file);
+    let out: &mut dyn io::Write = if args.len() >= 2 && &args[1] != "-" {
+        file = File::create(&args[1])?;
+        &mut file
+    } else {
+        stdout = io::stdout();
+        lock = stdout.lock();
+        &mut lock
+    };
+
+    const IMAGE_SIZE: u32 = 300;
+    const NUM_SAMPLES: u32 = 200;
+    const MAX_DEPTH: u32 = 50;
+
+    let (world, lights) = cornell_box();
+    let world = Arc::new(Bvh::new(world));
+
+    let cam = Camera::builder()
+        .origin(Vec3::new(278.0, 278.0, -800.0))
+        .target(Vec3::new(278.0, 278.0, 0.0))
+        .vup(Vec3::new(0.0, 1.0, 0.0))
+        .v_fov(f64::to_radians(40.0))
+        .aspect_ratio(1.0)
+        .aperture(0.0)
+        .focus_dist(10.0)
+        .build()
+        .unwrap();
+
+    let mut r = Raytracer::new(world, cam, IMAGE_SIZE, IMAGE_SIZE, NUM_SAMPLES, MAX_DEPTH);
+    r.lights = lights;
+    r.background = Arc::new(Colour::BLACK);
+
+    let pixels = r.render();
+
+    encode_webp(&pixels, IMAGE_SIZE, IMAGE_SIZE, out)
+}
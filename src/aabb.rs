@@ -0,0 +1,82 @@
+use crate::{Ray, Vec3};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Aabb {
+            min: Vec3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Vec3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    pub fn union(&self, other: Aabb) -> Aabb {
+        Aabb {
+            min: Vec3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: Vec3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    pub fn grow(&self, point: Vec3) -> Aabb {
+        self.union(Aabb {
+            min: point,
+            max: point,
+        })
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) / 2
+    }
+
+    pub fn extent(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let e = self.extent();
+        2.0 * (e.x * e.y + e.y * e.z + e.z * e.x)
+    }
+
+    /// Index of the axis along which the box is longest: 0, 1 or 2 for x, y or z.
+    pub fn longest_axis(&self) -> usize {
+        let e = self.extent();
+        if e.x >= e.y && e.x >= e.z {
+            0
+        } else if e.y >= e.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn hit(&self, ray: Ray, t_range: (f64, f64)) -> bool {
+        let (mut t_min, mut t_max) = t_range;
+        for axis in 0..3 {
+            let inv = ray.dir[axis].recip();
+            let mut t0 = (self.min[axis] - ray.orig[axis]) * inv;
+            let mut t1 = (self.max[axis] - ray.orig[axis]) * inv;
+            if inv < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // Written so that a NaN from 0 * inf leaves the bound untouched.
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max < t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
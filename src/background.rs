@@ -0,0 +1,133 @@
+use std::{f64::consts::PI, path::Path};
+
+use image::{
+    error::{ParameterError, ParameterErrorKind},
+    ImageError, ImageResult,
+};
+
+use crate::{Blend, Colour, Vec3};
+
+/// The light arriving from outside the scene along rays that don't hit anything.
+pub trait Background {
+    /// Radiance arriving from direction `dir`, which need not be a unit vector.
+    fn radiance(&self, dir: Vec3) -> Colour;
+}
+
+impl Background for Colour {
+    fn radiance(&self, _dir: Vec3) -> Colour {
+        *self
+    }
+}
+
+/// A vertical gradient from `bottom`, straight down, to `top`, straight up.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Gradient {
+    pub bottom: Colour,
+    pub top: Colour,
+}
+
+impl Gradient {
+    pub fn new(bottom: Colour, top: Colour) -> Self {
+        Gradient { bottom, top }
+    }
+
+    /// A pale blue sky.
+    pub fn sky() -> Self {
+        Gradient::new(Colour::WHITE, Colour::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Default for Gradient {
+    fn default() -> Self {
+        Gradient::sky()
+    }
+}
+
+impl Background for Gradient {
+    fn radiance(&self, dir: Vec3) -> Colour {
+        let t = (dir.unit().y + 1.0) / 2.0;
+        Blend(self.bottom, self.top).at(t)
+    }
+}
+
+/// An equirectangular (latitude/longitude) image wrapped around the scene, with +y up and the
+/// centre of the image in the -z direction.
+#[derive(Clone, Debug)]
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Colour>,
+    rotation: f64,
+}
+
+impl EnvironmentMap {
+    /// Build a map from linear radiance values, in rows from top to bottom. It must be at least
+    /// one pixel in each direction.
+    pub fn new(width: usize, height: usize, pixels: Vec<Colour>) -> Self {
+        assert!(width > 0 && height > 0, "environment map must not be empty");
+        assert_eq!(
+            width * height,
+            pixels.len(),
+            "environment map needs width × height pixels"
+        );
+        EnvironmentMap {
+            width,
+            height,
+            pixels,
+            rotation: 0.0,
+        }
+    }
+
+    /// Load a map from an image file, such as a Radiance `.hdr` photograph.
+    pub fn open(path: impl AsRef<Path>) -> ImageResult<Self> {
+        let image = image::open(path)?.into_rgb32f();
+        if image.width() == 0 || image.height() == 0 {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        }
+        let pixels = image
+            .pixels()
+            .map(|p| Colour::new(p[0] as f64, p[1] as f64, p[2] as f64))
+            .collect();
+        Ok(EnvironmentMap::new(
+            image.width() as usize,
+            image.height() as usize,
+            pixels,
+        ))
+    }
+
+    /// Turn the map by `angle` radians about the vertical axis.
+    pub fn rotated(mut self, angle: f64) -> Self {
+        self.rotation += angle;
+        self
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Colour {
+        self.pixels[y * self.width + x]
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn radiance(&self, dir: Vec3) -> Colour {
+        let dir = dir.unit();
+        let phi = dir.x.atan2(-dir.z) - self.rotation;
+        let theta = dir.y.clamp(-1.0, 1.0).acos();
+
+        // Continuous pixel coordinates, with pixel centres at half-integers.
+        let u = (phi / (2.0 * PI) + 0.5).rem_euclid(1.0) * self.width as f64 - 0.5;
+        let v = (theta / PI * self.height as f64 - 0.5).clamp(0.0, (self.height - 1) as f64);
+
+        let (x0, y0) = (u.floor(), v.floor());
+        let (fx, fy) = (u - x0, v - y0);
+        // Wrap horizontally around the seam, clamp vertically at the poles.
+        let x0 = (x0 as isize).rem_euclid(self.width as isize) as usize;
+        let x1 = (x0 + 1) % self.width;
+        let y0 = y0 as usize;
+        let y1 = (y0 + 1).min(self.height - 1);
+
+        let top = Blend(self.pixel(x0, y0), self.pixel(x1, y0)).at(fx);
+        let bottom = Blend(self.pixel(x0, y1), self.pixel(x1, y1)).at(fx);
+        Blend(top, bottom).at(fy)
+    }
+}
@@ -0,0 +1,263 @@
+use itertools::partition;
+
+use crate::{Aabb, Collider, Collision, Ray, Scene, Vec3};
+
+const BUCKETS: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+// Cost of visiting a node relative to testing a primitive, for the surface area heuristic.
+const TRAVERSAL_COST: f64 = 0.125;
+
+#[derive(Copy, Clone, Debug)]
+enum NodeKind {
+    Leaf { start: usize, len: usize },
+    // The left child always directly follows its parent.
+    Branch { right: usize, axis: usize },
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Node {
+    bounds: Aabb,
+    kind: NodeKind,
+}
+
+/// A flattened bounding volume hierarchy over a set of primitives identified by index, split
+/// using the surface area heuristic. Holds no primitives itself, so it can sit behind anything
+/// that can hand out bounding boxes.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct BvhTree {
+    nodes: Vec<Node>,
+    order: Vec<usize>,
+}
+
+impl BvhTree {
+    pub(crate) fn new(boxes: &[Aabb]) -> Self {
+        let mut order: Vec<usize> = (0..boxes.len()).collect();
+        let mut tree = BvhTree {
+            nodes: Vec::with_capacity(2 * boxes.len()),
+            order: Vec::new(),
+        };
+        if !boxes.is_empty() {
+            let centroids: Vec<Vec3> = boxes.iter().map(Aabb::centroid).collect();
+            tree.build(boxes, &centroids, &mut order, 0);
+        }
+        tree.order = order;
+        tree
+    }
+
+    pub(crate) fn bounds(&self) -> Option<Aabb> {
+        self.nodes.first().map(|n| n.bounds)
+    }
+
+    fn build(
+        &mut self,
+        boxes: &[Aabb],
+        centroids: &[Vec3],
+        indices: &mut [usize],
+        offset: usize,
+    ) -> usize {
+        let node = self.nodes.len();
+        let bounds = indices
+            .iter()
+            .map(|&i| boxes[i])
+            .reduce(|a, b| a.union(b))
+            .unwrap();
+        let leaf = Node {
+            bounds,
+            kind: NodeKind::Leaf {
+                start: offset,
+                len: indices.len(),
+            },
+        };
+        self.nodes.push(leaf);
+
+        if indices.len() == 1 {
+            return node;
+        }
+
+        let centroid_bounds = indices
+            .iter()
+            .map(|&i| Aabb::new(centroids[i], centroids[i]))
+            .reduce(|a, b| a.union(b))
+            .unwrap();
+        let axis = centroid_bounds.longest_axis();
+        let (lo, extent) = (centroid_bounds.min[axis], centroid_bounds.extent()[axis]);
+        if extent <= 0.0 {
+            // Every centroid coincides, so no split can separate them.
+            return node;
+        }
+
+        let bucket_of = |i: usize| {
+            let b = ((centroids[i][axis] - lo) / extent * BUCKETS as f64) as usize;
+            b.min(BUCKETS - 1)
+        };
+
+        let mut buckets: [(usize, Option<Aabb>); BUCKETS] = [(0, None); BUCKETS];
+        for &i in indices.iter() {
+            let (count, b) = &mut buckets[bucket_of(i)];
+            *count += 1;
+            *b = Some(b.map_or(boxes[i], |b| b.union(boxes[i])));
+        }
+
+        let side_cost = |side: &[(usize, Option<Aabb>)]| {
+            let (count, bounds) = side.iter().fold((0, None), |(n, acc), &(c, b)| {
+                let acc = match (acc, b) {
+                    (Some(a), Some(b)) => Some(Aabb::union(&a, b)),
+                    (a, b) => a.or(b),
+                };
+                (n + c, acc)
+            });
+            count as f64 * bounds.map_or(0.0, |b| b.surface_area())
+        };
+
+        let (split, cost) = (0..BUCKETS - 1)
+            .map(|k| {
+                let cost = TRAVERSAL_COST
+                    + (side_cost(&buckets[..=k]) + side_cost(&buckets[k + 1..]))
+                        / bounds.surface_area();
+                (k, cost)
+            })
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .unwrap();
+
+        if indices.len() <= MAX_LEAF_SIZE && cost >= indices.len() as f64 {
+            return node;
+        }
+
+        let mut mid = partition(indices.iter_mut(), |&i| bucket_of(i) <= split);
+        if mid == 0 || mid == indices.len() {
+            mid = indices.len() / 2;
+            indices.select_nth_unstable_by(mid, |&a, &b| {
+                centroids[a][axis].partial_cmp(&centroids[b][axis]).unwrap()
+            });
+        }
+
+        let (left, right) = indices.split_at_mut(mid);
+        self.build(boxes, centroids, left, offset);
+        let right = self.build(boxes, centroids, right, offset + mid);
+        self.nodes[node].kind = NodeKind::Branch { right, axis };
+
+        node
+    }
+
+    /// Walk the tree front to back, calling `hit` with the index of every primitive whose
+    /// bounds the ray passes through, and return the nearest collision found.
+    pub(crate) fn traverse<'a, F>(
+        &self,
+        ray: Ray,
+        t_range: (f64, f64),
+        mut hit: F,
+    ) -> Option<Collision<'a>>
+    where
+        F: FnMut(usize, (f64, f64)) -> Option<Collision<'a>>,
+    {
+        let mut closest: Option<Collision<'a>> = None;
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut stack = Vec::with_capacity(32);
+        stack.push(0);
+        while let Some(n) = stack.pop() {
+            let range = (t_range.0, closest.as_ref().map_or(t_range.1, |c| c.t));
+            let node = &self.nodes[n];
+            if !node.bounds.hit(ray, range) {
+                continue;
+            }
+
+            match node.kind {
+                NodeKind::Leaf { start, len } => {
+                    for &i in &self.order[start..start + len] {
+                        let range = (t_range.0, closest.as_ref().map_or(t_range.1, |c| c.t));
+                        if let Some(c) = hit(i, range) {
+                            closest = Some(c);
+                        }
+                    }
+                }
+                NodeKind::Branch { right, axis } => {
+                    // Push the far child first so the near one is visited first.
+                    if ray.dir[axis] < 0.0 {
+                        stack.push(n + 1);
+                        stack.push(right);
+                    } else {
+                        stack.push(right);
+                        stack.push(n + 1);
+                    }
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+/// A bounding volume hierarchy over a set of colliders. Behaves exactly like the [`Scene`] it
+/// was built from, but finds collisions in roughly logarithmic rather than linear time.
+pub struct Bvh {
+    objects: Scene,
+    // Colliders with no bounding box can't go in the tree and are checked on every ray.
+    unbounded: Vec<usize>,
+    tree: BvhTree,
+}
+
+impl Bvh {
+    pub fn new(objects: Scene) -> Self {
+        let mut unbounded = Vec::new();
+        let mut bounded = Vec::new();
+        let mut boxes = Vec::new();
+        for (i, o) in objects.iter().enumerate() {
+            match o.bounding_box() {
+                Some(b) => {
+                    bounded.push(i);
+                    boxes.push(b);
+                }
+                None => unbounded.push(i),
+            }
+        }
+
+        let mut tree = BvhTree::new(&boxes);
+        for i in tree.order.iter_mut() {
+            *i = bounded[*i];
+        }
+
+        Bvh {
+            objects,
+            unbounded,
+            tree,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+impl From<Scene> for Bvh {
+    fn from(objects: Scene) -> Self {
+        Bvh::new(objects)
+    }
+}
+
+impl Collider for Bvh {
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
+        let closest = self
+            .tree
+            .traverse(ray, t_range, |i, range| self.objects[i].collide(ray, range));
+
+        self.unbounded.iter().fold(closest, |closest, &i| {
+            let range = (t_range.0, closest.as_ref().map_or(t_range.1, |c| c.t));
+            self.objects[i].collide(ray, range).or(closest)
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if self.unbounded.is_empty() {
+            self.tree.bounds()
+        } else {
+            None
+        }
+    }
+}
@@ -10,6 +10,7 @@ pub struct Camera {
     offset_distr: Uniform<f64>,
     u: Vec3,
     v: Vec3,
+    shutter: (f64, f64),
 }
 
 #[derive(Default)]
@@ -21,6 +22,7 @@ pub struct CameraBuilder {
     aspect_ratio: Option<f64>,
     aperture: Option<f64>,
     focus_dist: Option<f64>,
+    shutter: Option<(f64, f64)>,
 }
 
 impl CameraBuilder {
@@ -52,6 +54,12 @@ impl CameraBuilder {
         self.focus_dist = Some(value);
         self
     }
+    /// The times the shutter opens and closes. Each ray is sent at a random time in between,
+    /// blurring anything which moves meanwhile. Defaults to an instant at time 0.
+    pub fn shutter(&mut self, open: f64, close: f64) -> &mut Self {
+        self.shutter = Some((open, close));
+        self
+    }
 
     pub fn build(&self) -> Result<Camera, String> {
         let origin = self
@@ -75,6 +83,10 @@ impl CameraBuilder {
         let focus_dist = self
             .focus_dist
             .ok_or_else(|| String::from("`focus_dist` must be initialized"))?;
+        let shutter = self.shutter.unwrap_or((0.0, 0.0));
+        if shutter.0 > shutter.1 || shutter.0.is_nan() || shutter.1.is_nan() {
+            return Err(String::from("`shutter` must not close before it opens"));
+        }
 
         let height = 2.0 * (v_fov / 2.0).tan();
         let width = aspect_ratio * height;
@@ -98,6 +110,7 @@ impl CameraBuilder {
             offset_distr,
             u,
             v,
+            shutter,
         })
     }
 }
@@ -107,15 +120,27 @@ impl Camera {
         CameraBuilder::default()
     }
 
-    pub fn ray(&self, h: f64, v: f64) -> Ray {
-        let u_offset = rand::thread_rng().sample(self.offset_distr);
-        let v_offset = rand::thread_rng().sample(self.offset_distr);
+    /// The ray through the point `(h, v)` of the viewport, where both run from 0 to 1, starting
+    /// from a point on the lens and at a time while the shutter is open, both chosen with `rng`,
+    /// which also gives the ray its [random bits](Ray::random).
+    pub fn ray<R: Rng + ?Sized>(&self, h: f64, v: f64, rng: &mut R) -> Ray {
+        let u_offset = rng.sample(self.offset_distr);
+        let v_offset = rng.sample(self.offset_distr);
         let offset = self.u * u_offset + self.v * v_offset;
 
         let origin = self.origin + offset;
+        let (open, close) = self.shutter;
+        // Only draw a time if there's a choice, so still images use the same random numbers.
+        let time = if open < close {
+            rng.gen_range(open..close)
+        } else {
+            open
+        };
         Ray::new(
             origin,
             self.llc + self.horizontal * h + self.vertical * v - origin,
         )
+        .with_time(time)
+        .with_random(rng.gen())
     }
 }
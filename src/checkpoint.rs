@@ -0,0 +1,191 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read, Write},
+};
+
+use crate::{mix, Accumulation, Colour, Pixel, Raytracer};
+
+const MAGIC: &[u8; 8] = b"rezckpt1";
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    /// The data isn't a checkpoint, or is cut short.
+    Format,
+    /// The checkpoint is of another image, and can't be carried on with this one.
+    Mismatch(String),
+    /// The raytracer has no [`scene_id`](Raytracer::scene_id), so there's no telling which
+    /// scene a checkpoint is of.
+    Unidentified,
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(e) => write!(f, "can't read checkpoint: {}", e),
+            CheckpointError::Format => write!(f, "not a checkpoint, or a damaged one"),
+            CheckpointError::Mismatch(message) => write!(f, "can't resume: {}", message),
+            CheckpointError::Unidentified => {
+                write!(
+                    f,
+                    "the raytracer has no scene id to tell its checkpoints apart by"
+                )
+            }
+        }
+    }
+}
+
+impl Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CheckpointError::Io(e) => Some(e),
+            CheckpointError::Format
+            | CheckpointError::Mismatch(_)
+            | CheckpointError::Unidentified => None,
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => CheckpointError::Format,
+            _ => CheckpointError::Io(e),
+        }
+    }
+}
+
+impl Raytracer {
+    /// A hash of everything that decides what each sample of the image comes out as: the
+    /// settings, and the [`scene_id`](Raytracer::scene_id) standing for the rest. Samples
+    /// taken by raytracers with different fingerprints can't be mixed. Without a scene id,
+    /// there is no fingerprint.
+    ///
+    /// The number of samples per pixel isn't included, so a finished render can be resumed
+    /// with more.
+    pub fn fingerprint(&self) -> Option<u64> {
+        let mut hash = mix(self.seed);
+        let mut add = |v: u64| hash = mix(hash ^ v);
+        add(self.scene_id?);
+        add(self.width as u64);
+        add(self.height as u64);
+        let frame = self.frame();
+        add(frame.x as u64);
+        add(frame.y as u64);
+        add(self.bounce_depth as u64);
+        if let Some(adaptive) = self.adaptive {
+            add(adaptive.threshold.to_bits());
+            add(adaptive.min_samples as u64);
+        }
+        Some(hash)
+    }
+
+    /// Save `image`, part way through rendering, so that rendering can be carried on later
+    /// with [`resume`](Raytracer::resume). Fails with [`CheckpointError::Unidentified`] if
+    /// the raytracer has no scene id.
+    pub fn checkpoint(&self, image: &Accumulation, mut out: impl Write) -> io::Result<()> {
+        let fingerprint = self.fingerprint().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, CheckpointError::Unidentified)
+        })?;
+        out.write_all(MAGIC)?;
+        out.write_all(&fingerprint.to_le_bytes())?;
+        image.write_to(&mut out)?;
+        out.flush()
+    }
+
+    /// Load an image saved by [`checkpoint`](Raytracer::checkpoint), ready to carry on with
+    /// [`render_from`](Raytracer::render_from). Refuses checkpoints of other scenes, or made
+    /// with other settings.
+    pub fn resume(&self, mut input: impl Read) -> Result<Accumulation, CheckpointError> {
+        let expected = self.fingerprint().ok_or(CheckpointError::Unidentified)?;
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(CheckpointError::Format);
+        }
+        let fingerprint = read_u64(&mut input)?;
+        let image = Accumulation::read_from(&mut input)?;
+
+        let frame = self.frame();
+        if (image.width, image.height) != (frame.width, frame.height) {
+            return Err(CheckpointError::Mismatch(format!(
+                "the checkpoint is {}x{} pixels, not {}x{}",
+                image.width, image.height, frame.width, frame.height
+            )));
+        }
+        if fingerprint != expected {
+            return Err(CheckpointError::Mismatch(
+                "the checkpoint is of a different scene, or was made with different settings"
+                    .to_string(),
+            ));
+        }
+        Ok(image)
+    }
+}
+
+/// A hash of `description` to use as a [`Raytracer::scene_id`], the same on every run and
+/// every machine.
+pub fn scene_id(description: impl AsRef<[u8]>) -> u64 {
+    let bytes = description.as_ref();
+    let mut hash = mix(bytes.len() as u64);
+    for chunk in bytes.chunks(8) {
+        let mut word = [0; 8];
+        word[..chunk.len()].copy_from_slice(chunk);
+        hash = mix(hash ^ u64::from_le_bytes(word));
+    }
+    hash
+}
+
+impl Accumulation {
+    /// Write the image in the binary form [`read_from`](Accumulation::read_from) reads, with
+    /// every sample's contribution kept exactly.
+    pub fn write_to(&self, mut out: impl Write) -> io::Result<()> {
+        for v in [self.width, self.height, self.samples] {
+            out.write_all(&v.to_le_bytes())?;
+        }
+        for p in &self.pixels {
+            for v in [p.colour.r, p.colour.g, p.colour.b, p.brightness, p.m2] {
+                out.write_all(&v.to_le_bytes())?;
+            }
+            out.write_all(&p.samples.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from(mut input: impl Read) -> io::Result<Accumulation> {
+        let (width, height) = (read_u32(&mut input)?, read_u32(&mut input)?);
+        let samples = read_u32(&mut input)?;
+
+        // Grow the buffer as pixels arrive, rather than trusting the size up front.
+        let count = width as usize * height as usize;
+        let mut pixels = Vec::with_capacity(count.min(1 << 20));
+        for _ in 0..count {
+            let mut f = || read_u64(&mut input).map(f64::from_bits);
+            pixels.push(Pixel {
+                colour: Colour::new(f()?, f()?, f()?),
+                brightness: f()?,
+                m2: f()?,
+                samples: read_u32(&mut input)?,
+            });
+        }
+        Ok(Accumulation {
+            width,
+            height,
+            samples,
+            pixels,
+        })
+    }
+}
+
+pub(crate) fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
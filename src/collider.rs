@@ -1,17 +1,28 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{f64::consts::PI, fmt::Debug, sync::Arc};
 
-use crate::{Colour, Material, Ray, Vec3};
+use rand::RngCore;
+
+use crate::{Aabb, Colour, Material, Ray, Vec3};
 
 pub struct Collision<'a> {
     pub point: Vec3,
     pub normal: Vec3,
     pub t: f64,
     pub front: bool,
+    /// Weights of each vertex at the collision point, for colliders made of triangles.
+    pub barycentric: Option<[f64; 3]>,
+    /// Texture coordinates at the collision point, each usually between 0 and 1.
+    pub uv: (f64, f64),
     pub material: &'a dyn Material,
 }
 
 impl Collision<'_> {
-    fn from_ray(ray: Ray, t: f64, outward_normal: Vec3, material: &dyn Material) -> Collision {
+    pub(crate) fn from_ray(
+        ray: Ray,
+        t: f64,
+        outward_normal: Vec3,
+        material: &dyn Material,
+    ) -> Collision<'_> {
         let front = ray.dir.dot(outward_normal) < 0.0;
         let normal = if front {
             outward_normal
@@ -23,17 +34,67 @@ impl Collision<'_> {
             normal,
             t,
             front,
+            barycentric: None,
+            uv: (0.0, 0.0),
             material,
         }
     }
 
-    pub fn scatter(&self, ray: Ray) -> Option<(Colour, Ray)> {
-        self.material.scatter(ray, &self)
+    /// Replace the normal used for shading while keeping it on the same side as the ray.
+    pub(crate) fn with_shading_normal(mut self, outward_normal: Vec3) -> Self {
+        self.normal = if self.front {
+            outward_normal
+        } else {
+            -outward_normal
+        };
+        self
+    }
+
+    pub fn scatter(&self, ray: Ray, rng: &mut dyn RngCore) -> Option<(Colour, Ray)> {
+        self.material.scatter(ray, self, rng)
+    }
+
+    pub fn emitted(&self) -> Colour {
+        self.material.emitted(self)
     }
 }
 
 pub trait Collider {
-    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision>;
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>>;
+
+    /// The axis-aligned box enclosing everything this collider can hit, or `None` if it is
+    /// unbounded.
+    fn bounding_box(&self) -> Option<Aabb>;
+}
+
+impl<C: Collider + ?Sized> Collider for &C {
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
+        (**self).collide(ray, t_range)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
+}
+
+impl<C: Collider + ?Sized> Collider for Box<C> {
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
+        (**self).collide(ray, t_range)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
+}
+
+impl<C: Collider + ?Sized> Collider for Arc<C> {
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
+        (**self).collide(ray, t_range)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        (**self).bounding_box()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -57,43 +118,126 @@ impl<M: Material> Sphere<M> {
 }
 
 impl<M: Material> Collider for Sphere<M> {
-    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision> {
-        let a = ray.dir.squared();
-        let h = (ray.orig - self.centre).dot(ray.dir); // h = b/2
-        let c = (ray.orig - self.centre).squared() - self.radius.powi(2);
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
+        collide_sphere(
+            self.centre,
+            self.radius,
+            self.material.as_ref(),
+            ray,
+            t_range,
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.radius.abs();
+        let r = Vec3::new(r, r, r);
+        Some(Aabb::new(self.centre - r, self.centre + r))
+    }
+}
+
+/// A sphere moving in a straight line at constant speed, from `centre.0` at `time.0` to
+/// `centre.1` at `time.1`. Before and after, it holds still at the end it is nearest.
+#[derive(Clone, Debug)]
+pub struct MovingSphere<M>
+where
+    M: Material,
+{
+    pub centre: (Vec3, Vec3),
+    pub time: (f64, f64),
+    pub radius: f64,
+    pub material: Arc<M>,
+}
 
-        let discriminant = h.powi(2) - a * c;
+impl<M: Material> MovingSphere<M> {
+    pub fn new(centre: (Vec3, Vec3), time: (f64, f64), radius: f64, material: Arc<M>) -> Self {
+        MovingSphere {
+            centre,
+            time,
+            radius,
+            material,
+        }
+    }
 
-        if discriminant < 0.0 {
-            return None;
+    pub fn centre_at(&self, time: f64) -> Vec3 {
+        let (c0, c1) = self.centre;
+        let (t0, t1) = self.time;
+        if t0 == t1 {
+            return c0;
         }
+        c0 + (c1 - c0) * ((time - t0) / (t1 - t0)).clamp(0.0, 1.0)
+    }
+}
 
-        let (t1, t2) = (
-            (-h - discriminant.sqrt()) / a,
-            (-h + discriminant.sqrt()) / a,
-        );
-        let t = if t_range.0 <= t1 && t1 <= t_range.1 {
-            t1
-        } else if t_range.0 <= t2 && t2 <= t_range.1 {
-            t2
-        } else {
-            return None;
-        };
+impl<M: Material> Collider for MovingSphere<M> {
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
+        let centre = self.centre_at(ray.time);
+        collide_sphere(centre, self.radius, self.material.as_ref(), ray, t_range)
+    }
 
-        Some(Collision::from_ray(
-            ray,
-            t,
-            (ray.at(t) - self.centre) / self.radius,
-            self.material.as_ref(),
-        ))
+    /// Bounds the whole path, which is everywhere the sphere can be at any time.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.radius.abs();
+        let r = Vec3::new(r, r, r);
+        let (c0, c1) = self.centre;
+        Some(Aabb::new(c0 - r, c0 + r).union(Aabb::new(c1 - r, c1 + r)))
+    }
+}
+
+fn collide_sphere(
+    centre: Vec3,
+    radius: f64,
+    material: &dyn Material,
+    ray: Ray,
+    t_range: (f64, f64),
+) -> Option<Collision<'_>> {
+    let a = ray.dir.squared();
+    let h = (ray.orig - centre).dot(ray.dir); // h = b/2
+    let c = (ray.orig - centre).squared() - radius.powi(2);
+
+    let discriminant = h.powi(2) - a * c;
+
+    if discriminant < 0.0 {
+        return None;
     }
+
+    let (t1, t2) = (
+        (-h - discriminant.sqrt()) / a,
+        (-h + discriminant.sqrt()) / a,
+    );
+    let t = if t_range.0 <= t1 && t1 <= t_range.1 {
+        t1
+    } else if t_range.0 <= t2 && t2 <= t_range.1 {
+        t2
+    } else {
+        return None;
+    };
+
+    let outward_normal = (ray.at(t) - centre) / radius;
+    let mut col = Collision::from_ray(ray, t, outward_normal, material);
+    col.uv = sphere_uv(outward_normal);
+    Some(col)
+}
+
+/// Texture coordinates for a point on the unit sphere: `u` runs once around the vertical axis
+/// from -x, and `v` from the bottom to the top.
+fn sphere_uv(p: Vec3) -> (f64, f64) {
+    let theta = (-p.y).clamp(-1.0, 1.0).acos();
+    let phi = (-p.z).atan2(p.x) + PI;
+    (phi / (2.0 * PI), theta / PI)
 }
 
 pub type Scene = Vec<Box<dyn Collider + Send + Sync>>;
-impl Collider for &Scene {
-    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision> {
+impl Collider for Scene {
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
         self.iter()
             .filter_map(|e| e.collide(ray, t_range))
             .min_by(|a, b| a.t.partial_cmp(&b.t).unwrap())
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.iter()
+            .map(|e| e.bounding_box())
+            .reduce(|a, b| Some(a?.union(b?)))
+            .flatten()
+    }
 }
@@ -19,6 +19,11 @@ impl Colour {
         (f(self.r), f(self.g), f(self.b))
     }
 
+    pub fn to_48bit_rgb(&self) -> (u16, u16, u16) {
+        let f = |v: f64| (65535.0 * v.clamp(0.0, 1.0)).round() as u16;
+        (f(self.r), f(self.g), f(self.b))
+    }
+
     pub const ZERO: Colour = Colour {
         r: 0.0,
         g: 0.0,
@@ -33,6 +38,11 @@ impl Colour {
         b: 1.0,
     };
 
+    /// How bright the colour looks, weighting each channel as in Rec. 709.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
     pub fn scale(&self, rhs: Colour) -> Colour {
         Colour {
             r: self.r * rhs.r,
@@ -0,0 +1,283 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::{TcpListener, TcpStream},
+    ops::ControlFlow,
+    sync::{mpsc, Arc, Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crate::{read_u32, read_u64, Accumulation, Raytracer, Region, Silent};
+
+const MAGIC: &[u8; 8] = b"rezdist1";
+
+#[derive(Debug)]
+pub enum DistributedError {
+    Io(io::Error),
+    /// The other end isn't a coordinator.
+    Protocol,
+    /// The raytracer the coordinator described couldn't be set up.
+    Setup(Box<dyn Error + Send + Sync>),
+    /// The raytracer set up here isn't the same as the coordinator's.
+    Mismatch,
+}
+
+impl fmt::Display for DistributedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DistributedError::Io(e) => write!(f, "lost the coordinator: {}", e),
+            DistributedError::Protocol => write!(f, "not connected to a rez coordinator"),
+            DistributedError::Setup(e) => write!(f, "can't set up the render: {}", e),
+            DistributedError::Mismatch => write!(
+                f,
+                "the scene here is different to the coordinator's, or was set up differently"
+            ),
+        }
+    }
+}
+
+impl Error for DistributedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DistributedError::Io(e) => Some(e),
+            DistributedError::Setup(e) => Some(e.as_ref()),
+            DistributedError::Protocol | DistributedError::Mismatch => None,
+        }
+    }
+}
+
+impl From<io::Error> for DistributedError {
+    fn from(e: io::Error) -> Self {
+        DistributedError::Io(e)
+    }
+}
+
+/// Tiles waiting to be rendered, shared between the connections to each worker.
+struct Jobs {
+    pending: Vec<Region>,
+    /// Tiles not yet rendered, including those being worked on.
+    unfinished: usize,
+}
+
+/// Render `raytracer`'s image on workers connecting to `listener`, each given square tiles
+/// `job_size` pixels across to render in full, one at a time.
+///
+/// Workers are sent `setup` to build their own copy of the raytracer from, usually the
+/// command line the coordinator was started with, and check that they ended up with the
+/// same scene by comparing [fingerprints](Raytracer::fingerprint), so the raytracer needs a
+/// [`scene_id`](Raytracer::scene_id). Workers can join at any
+/// time, and if one disconnects part way through a tile, that tile is given to another.
+/// Returns once every tile is done, however long that takes.
+///
+/// Each pixel is rendered as it would be on one machine, so the image is the same as from
+/// [`render_progressive`](Raytracer::render_progressive).
+pub fn coordinate(
+    raytracer: &Raytracer,
+    listener: TcpListener,
+    setup: &[String],
+    job_size: u32,
+) -> io::Result<Accumulation> {
+    let frame = raytracer.frame();
+    let mut tiles = raytracer.tile_order.tiles(frame, job_size);
+    // Workers take tiles from the end.
+    tiles.reverse();
+    let jobs = Arc::new((
+        Mutex::new(Jobs {
+            unfinished: tiles.len(),
+            pending: tiles,
+        }),
+        Condvar::new(),
+    ));
+    let fingerprint = raytracer.fingerprint().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the raytracer has no scene id for workers to check theirs against",
+        )
+    })?;
+    let (done, finished) = mpsc::channel();
+
+    // Poll for workers, so that the listener can be given up once the image is done.
+    listener.set_nonblocking(true)?;
+    let accept = {
+        let jobs = jobs.clone();
+        let setup = setup.to_vec();
+        thread::spawn(move || -> io::Result<()> {
+            while jobs.0.lock().unwrap().unfinished > 0 {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        stream.set_nonblocking(false)?;
+                        let (jobs, setup, done) = (jobs.clone(), setup.clone(), done.clone());
+                        thread::spawn(move || {
+                            // A worker going away only matters for the tile it had, which
+                            // `serve` puts back.
+                            let _ = serve(stream, &jobs, &setup, fingerprint, &done);
+                        });
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(10))
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        })
+    };
+
+    let progress = &raytracer.progress;
+    progress.started(frame.width as u64 * frame.height as u64);
+    let mut image = Accumulation::new(frame.width, frame.height);
+    // Every tile ends up here, unless the listener fails and no more workers can join.
+    for (tile, pixels) in finished.iter() {
+        image.paste(tile.x - frame.x, tile.y - frame.y, &pixels);
+        let samples = pixels.sample_counts().iter().map(|&n| n as u64).sum();
+        progress.advanced(tile.width as u64 * tile.height as u64, samples);
+
+        let (lock, wake) = &*jobs;
+        let mut jobs = lock.lock().unwrap();
+        jobs.unfinished -= 1;
+        if jobs.unfinished == 0 {
+            wake.notify_all();
+            break;
+        }
+    }
+    progress.finished();
+
+    accept.join().unwrap()?;
+    if jobs.0.lock().unwrap().unfinished > 0 {
+        return Err(io::Error::other("stopped listening for workers"));
+    }
+    Ok(image)
+}
+
+/// Hand tiles to one worker until they run out, or the worker stops answering.
+fn serve(
+    stream: TcpStream,
+    jobs: &(Mutex<Jobs>, Condvar),
+    setup: &[String],
+    fingerprint: u64,
+    done: &mpsc::Sender<(Region, Accumulation)>,
+) -> io::Result<()> {
+    let mut input = BufReader::new(stream.try_clone()?);
+    let mut out = BufWriter::new(stream);
+
+    out.write_all(MAGIC)?;
+    out.write_all(&fingerprint.to_le_bytes())?;
+    out.write_all(&(setup.len() as u32).to_le_bytes())?;
+    for arg in setup {
+        out.write_all(&(arg.len() as u32).to_le_bytes())?;
+        out.write_all(arg.as_bytes())?;
+    }
+    out.flush()?;
+    // The worker says it's ready once it has checked it has the same scene.
+    let mut ready = [0];
+    input.read_exact(&mut ready)?;
+
+    let (lock, wake) = jobs;
+    loop {
+        let tile = {
+            let mut jobs = lock.lock().unwrap();
+            loop {
+                if let Some(tile) = jobs.pending.pop() {
+                    break tile;
+                }
+                if jobs.unfinished == 0 {
+                    return Ok(());
+                }
+                // Wait in case another worker fails and its tile needs a new home.
+                jobs = wake.wait(jobs).unwrap();
+            }
+        };
+
+        let rendered = send_job(&mut out, tile).and_then(|_| Accumulation::read_from(&mut input));
+        match rendered {
+            Ok(pixels) if (pixels.width(), pixels.height()) == (tile.width, tile.height) => {
+                // Finished tiles are counted off by `coordinate`, which wakes anyone waiting
+                // once the last is in.
+                let _ = done.send((tile, pixels));
+            }
+            rendered => {
+                lock.lock().unwrap().pending.push(tile);
+                wake.notify_one();
+                return match rendered {
+                    Err(e) => Err(e),
+                    Ok(_) => Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "worker sent a tile of the wrong size",
+                    )),
+                };
+            }
+        }
+    }
+}
+
+fn send_job(out: &mut impl Write, tile: Region) -> io::Result<()> {
+    for v in [tile.x, tile.y, tile.width, tile.height] {
+        out.write_all(&v.to_le_bytes())?;
+    }
+    out.flush()
+}
+
+/// Render tiles for the coordinator at the other end of `stream` until it has no more to
+/// give, returning how many were done.
+///
+/// The raytracer is made by `load` from the setup the coordinator sends, and must come out
+/// the same as the coordinator's. Each tile is rendered with all of the raytracer's threads.
+pub fn work(
+    stream: TcpStream,
+    load: impl FnOnce(&[String]) -> Result<Raytracer, Box<dyn Error + Send + Sync>>,
+) -> Result<usize, DistributedError> {
+    let mut input = BufReader::new(stream.try_clone()?);
+    let mut out = BufWriter::new(stream);
+
+    let mut magic = [0; 8];
+    input
+        .read_exact(&mut magic)
+        .map_err(|_| DistributedError::Protocol)?;
+    if &magic != MAGIC {
+        return Err(DistributedError::Protocol);
+    }
+    let fingerprint = read_u64(&mut input)?;
+    let setup = (0..read_u32(&mut input)?)
+        .map(|_| {
+            let mut arg = vec![0; read_u32(&mut input)? as usize];
+            input.read_exact(&mut arg)?;
+            String::from_utf8(arg).map_err(|_| DistributedError::Protocol)
+        })
+        .collect::<Result<Vec<_>, DistributedError>>()?;
+
+    let mut raytracer = load(&setup).map_err(DistributedError::Setup)?;
+    if raytracer.fingerprint() != Some(fingerprint) {
+        return Err(DistributedError::Mismatch);
+    }
+    raytracer.progress = Arc::new(Silent);
+    out.write_all(&[1])?;
+    out.flush()?;
+
+    let mut tiles = 0;
+    loop {
+        // The coordinator hangs up when the image is done.
+        let x = match read_u32(&mut input) {
+            Ok(x) => x,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(tiles),
+            Err(e) => return Err(e.into()),
+        };
+        let (y, width, height) = (
+            read_u32(&mut input)?,
+            read_u32(&mut input)?,
+            read_u32(&mut input)?,
+        );
+        let tile = Region::new(x, y, width, height);
+        if !tile.fits(raytracer.width, raytracer.height) {
+            return Err(DistributedError::Protocol);
+        }
+        raytracer.region = Some(tile);
+
+        let pixels = raytracer
+            .render_progressive(raytracer.samples_per_pixel, |_| ControlFlow::Continue(()));
+        pixels.write_to(&mut out)?;
+        out.flush()?;
+        tiles += 1;
+    }
+}
@@ -1,5 +1,9 @@
 use crate::Colour;
-use std::io;
+use exr::prelude::{
+    f16, Compression, Encoding, Image, LineOrder, SpecificChannels, Vec2, WritableImage,
+};
+use image::{codecs::hdr::HdrEncoder, Rgb};
+use std::io::{self, Cursor};
 
 pub fn encode_ppm(
     data: &[Colour],
@@ -11,8 +15,7 @@ pub fn encode_ppm(
 
     data.iter()
         .map(Colour::to_24bit_rgb)
-        .map(|(r, g, b)| writeln!(out, "{r} {g} {b}"))
-        .collect::<Result<_, _>>()
+        .try_for_each(|(r, g, b)| writeln!(out, "{r} {g} {b}"))
 }
 
 pub fn encode_webp(
@@ -31,3 +34,154 @@ pub fn encode_webp(
 
     out.write(&enc.encode_lossless()).map(|_| ())
 }
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum BitDepth {
+    #[default]
+    Eight,
+    Sixteen,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct PngOptions {
+    pub bit_depth: BitDepth,
+    /// Add a fully opaque alpha channel, for tools which only accept RGBA.
+    pub alpha: bool,
+}
+
+pub fn encode_png(data: &[Colour], width: u32, height: u32, out: impl io::Write) -> io::Result<()> {
+    encode_png_with(data, width, height, out, PngOptions::default())
+}
+
+pub fn encode_png_with(
+    data: &[Colour],
+    width: u32,
+    height: u32,
+    out: impl io::Write,
+    options: PngOptions,
+) -> io::Result<()> {
+    let mut enc = png::Encoder::new(out, width, height);
+    enc.set_color(if options.alpha {
+        png::ColorType::Rgba
+    } else {
+        png::ColorType::Rgb
+    });
+
+    let channels = if options.alpha { 4 } else { 3 };
+    let image = match options.bit_depth {
+        BitDepth::Eight => {
+            enc.set_depth(png::BitDepth::Eight);
+            data.iter()
+                .map(Colour::to_24bit_rgb)
+                .flat_map(|(r, g, b)| IntoIterator::into_iter([r, g, b, u8::MAX]).take(channels))
+                .collect::<Vec<u8>>()
+        }
+        BitDepth::Sixteen => {
+            enc.set_depth(png::BitDepth::Sixteen);
+            data.iter()
+                .map(Colour::to_48bit_rgb)
+                .flat_map(|(r, g, b)| IntoIterator::into_iter([r, g, b, u16::MAX]).take(channels))
+                // PNG stores samples big-endian.
+                .flat_map(u16::to_be_bytes)
+                .collect::<Vec<u8>>()
+        }
+    };
+
+    let mut writer = enc.write_header()?;
+    writer.write_image_data(&image)?;
+    writer.finish().map_err(io::Error::from)
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Precision {
+    /// 16-bit floating point
+    #[default]
+    Half,
+    /// 32-bit floating point
+    Full,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum ExrCompression {
+    None,
+    /// Lossless zlib compression, in blocks of 16 scanlines.
+    #[default]
+    Zip,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ExrOptions {
+    pub precision: Precision,
+    pub compression: ExrCompression,
+}
+
+/// Encode linear radiance, such as from [`Raytracer::render_linear`](crate::Raytracer::render_linear), as an
+/// OpenEXR image.
+pub fn encode_exr(data: &[Colour], width: u32, height: u32, out: impl io::Write) -> io::Result<()> {
+    encode_exr_with(data, width, height, out, ExrOptions::default())
+}
+
+pub fn encode_exr_with(
+    data: &[Colour],
+    width: u32,
+    height: u32,
+    mut out: impl io::Write,
+    options: ExrOptions,
+) -> io::Result<()> {
+    let size = (width as usize, height as usize);
+    let encoding = Encoding {
+        compression: match options.compression {
+            ExrCompression::None => Compression::Uncompressed,
+            ExrCompression::Zip => Compression::ZIP16,
+        },
+        line_order: LineOrder::Increasing,
+        ..Encoding::default()
+    };
+    let pixel = |p: Vec2<usize>| data[p.y() * size.0 + p.x()];
+
+    // The encoder needs to seek, so write to memory first.
+    let mut buf = Cursor::new(Vec::new());
+    let written = match options.precision {
+        Precision::Half => {
+            let f = |v: f64| f16::from_f64(v);
+            let channels = SpecificChannels::rgb(|p| {
+                let c = pixel(p);
+                (f(c.r), f(c.g), f(c.b))
+            });
+            Image::from_encoded_channels(size, encoding, channels)
+                .write()
+                .to_buffered(&mut buf)
+        }
+        Precision::Full => {
+            let channels = SpecificChannels::rgb(|p| {
+                let c = pixel(p);
+                (c.r as f32, c.g as f32, c.b as f32)
+            });
+            Image::from_encoded_channels(size, encoding, channels)
+                .write()
+                .to_buffered(&mut buf)
+        }
+    };
+    written.map_err(|e| match e {
+        exr::error::Error::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidInput, e),
+    })?;
+
+    out.write_all(buf.get_ref())
+}
+
+/// Encode linear radiance, such as from [`Raytracer::render_linear`](crate::Raytracer::render_linear), as a
+/// run-length encoded Radiance RGBE image.
+pub fn encode_hdr(data: &[Colour], width: u32, height: u32, out: impl io::Write) -> io::Result<()> {
+    let image = data
+        .iter()
+        .map(|c| Rgb([c.r as f32, c.g as f32, c.b as f32]))
+        .collect::<Vec<_>>();
+
+    HdrEncoder::new(out)
+        .encode(&image, width as usize, height as usize)
+        .map_err(|e| match e {
+            image::ImageError::IoError(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidInput, e),
+        })
+}
@@ -0,0 +1,185 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{Aabb, Collider, Collision, Ray, Transform, Vec3};
+
+/// A shared object placed in the scene by a [`Transform`] from its own space.
+///
+/// Any number of instances can share one object, so a detailed mesh can appear many times
+/// while only being held in memory once.
+#[derive(Clone)]
+pub struct Instance {
+    pub object: Arc<dyn Collider + Send + Sync>,
+    transform: Transform,
+    bounds: Option<Aabb>,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Collider + Send + Sync>, transform: Transform) -> Self {
+        let bounds = object.bounding_box().map(|b| transform.aabb(b));
+        Instance {
+            object,
+            transform,
+            bounds,
+        }
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+}
+
+impl Collider for Instance {
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
+        collide_transformed(self.object.as_ref(), self.transform, ray, t_range)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds
+    }
+}
+
+fn collide_transformed(
+    object: &(dyn Collider + Send + Sync),
+    transform: Transform,
+    ray: Ray,
+    t_range: (f64, f64),
+) -> Option<Collision<'_>> {
+    // Directions aren't normalised either way, so `t` means the same in both spaces.
+    let local = transform.inverse().ray(ray);
+    let mut col = object.collide(local, t_range)?;
+    col.point = ray.at(col.t);
+    col.normal = transform.normal(col.normal).unit();
+    Some(col)
+}
+
+/// Where an animated object is at one moment: scaled, then rotated by `angle` radians about
+/// `axis` through its origin, then moved by `translation`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Pose {
+    pub translation: Vec3,
+    pub axis: Vec3,
+    pub angle: f64,
+    pub scale: Vec3,
+}
+
+impl Pose {
+    pub fn transform(&self) -> Transform {
+        Transform::translate(self.translation)
+            * Transform::rotate(self.axis, self.angle)
+            * Transform::scale(self.scale)
+    }
+
+    /// The pose a fraction `f` of the way from `self` to `other`. Angles are interpolated
+    /// directly rather than taking the shortest way round, so a pose can turn through more
+    /// than half a revolution between keyframes, as a spinning part does.
+    pub fn lerp(&self, other: &Pose, f: f64) -> Pose {
+        let lerp = |a: Vec3, b: Vec3| a + (b - a) * f;
+        let axis = lerp(self.axis.unit(), other.axis.unit());
+        Pose {
+            translation: lerp(self.translation, other.translation),
+            // Opposite axes cancel out halfway, leaving no axis to turn about.
+            axis: if axis.small() { self.axis } else { axis },
+            angle: self.angle + (other.angle - self.angle) * f,
+            scale: lerp(self.scale, other.scale),
+        }
+    }
+}
+
+impl Default for Pose {
+    fn default() -> Self {
+        Pose {
+            translation: Vec3::ZERO,
+            axis: Vec3::new(0.0, 1.0, 0.0),
+            angle: 0.0,
+            scale: Vec3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// A shared object whose pose changes over time, interpolated between keyframes, for motion
+/// blur. Before the first keyframe and after the last, it holds still.
+#[derive(Clone)]
+pub struct AnimatedInstance {
+    pub object: Arc<dyn Collider + Send + Sync>,
+    keyframes: Vec<(f64, Pose)>,
+    bounds: Option<Aabb>,
+}
+
+impl AnimatedInstance {
+    /// Animate `object` through `keyframes`, each a time and the pose at that time. Panics if
+    /// there are none.
+    pub fn new(object: Arc<dyn Collider + Send + Sync>, mut keyframes: Vec<(f64, Pose)>) -> Self {
+        assert!(
+            !keyframes.is_empty(),
+            "animation needs at least one keyframe"
+        );
+        keyframes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        let mut instance = AnimatedInstance {
+            object,
+            keyframes,
+            bounds: None,
+        };
+        instance.bounds = instance.sweep_bounds();
+        instance
+    }
+
+    pub fn pose_at(&self, time: f64) -> Pose {
+        let keys = &self.keyframes;
+        let next = keys.partition_point(|(t, _)| *t <= time);
+        if next == 0 {
+            return keys[0].1;
+        }
+        if next == keys.len() {
+            return keys[next - 1].1;
+        }
+        let ((t0, p0), (t1, p1)) = (keys[next - 1], keys[next]);
+        p0.lerp(&p1, (time - t0) / (t1 - t0))
+    }
+
+    pub fn transform_at(&self, time: f64) -> Transform {
+        self.pose_at(time).transform()
+    }
+
+    /// Bounds of the object over the whole animation, found by posing it at enough moments
+    /// that it never turns more than a degree between them, and allowing for the corners of
+    /// its box cutting across the arcs they sweep between.
+    fn sweep_bounds(&self) -> Option<Aabb> {
+        let object = self.object.bounding_box()?;
+        let step = PI / 180.0;
+        let mut bounds: Option<Aabb> = None;
+        let mut add = |pose: Pose| {
+            let b = pose.transform().aabb(object);
+            bounds = Some(bounds.map_or(b, |a| a.union(b)));
+        };
+
+        add(self.keyframes[0].1);
+        for pair in self.keyframes.windows(2) {
+            let ((_, p0), (_, p1)) = (pair[0], pair[1]);
+            let steps = ((p1.angle - p0.angle).abs() / step).ceil().clamp(1.0, 1e5) as usize;
+            for i in 1..=steps {
+                add(p0.lerp(&p1, i as f64 / steps as f64));
+            }
+        }
+
+        // A chord of an arc of one degree strays from it by under 4e-5 of its radius.
+        let bounds = bounds?;
+        let slack = bounds.extent().length() * 4e-5;
+        let slack = Vec3::new(slack, slack, slack);
+        Some(Aabb::new(bounds.min - slack, bounds.max + slack))
+    }
+}
+
+impl Collider for AnimatedInstance {
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
+        collide_transformed(
+            self.object.as_ref(),
+            self.transform_at(ray.time),
+            ray,
+            t_range,
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds
+    }
+}
@@ -0,0 +1,279 @@
+use rand::{Rng, RngCore};
+
+use crate::{Blend, Collision, Colour, Ray, Raytracer, Vec3};
+
+/// An algorithm for working out what a camera ray sees.
+pub trait Integrator {
+    /// The colour seen along the camera ray `ray` through the scene rendered by `raytracer`,
+    /// drawing any random numbers needed from `rng`. Averaged over all the rays through a
+    /// pixel, this gives the pixel's colour.
+    fn radiance(&self, raytracer: &Raytracer, ray: Ray, rng: &mut dyn RngCore) -> Colour;
+}
+
+/// Rays closer than this to where they start are ignored, so that rays leaving a surface don't
+/// hit it again straight away due to rounding.
+const T_MIN: f64 = 0.001;
+
+/// Physically based unidirectional path tracing, sending shadow rays to the renderer's
+/// lights and scattering up to `bounce_depth` times.
+///
+/// After `roulette_depth` bounces, paths are ended at random with a probability that grows as
+/// less of their light can reach the camera, and the survivors brightened to make up for it.
+/// This stops paths wasting time on bounces that barely matter, without biasing the image.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct PathTracer {
+    pub roulette_depth: u32,
+}
+
+impl PathTracer {
+    pub fn new(roulette_depth: u32) -> Self {
+        PathTracer { roulette_depth }
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        PathTracer::new(3)
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(&self, rt: &Raytracer, mut ray: Ray, rng: &mut dyn RngCore) -> Colour {
+        let mut radiance = Colour::BLACK;
+        // The fraction of light arriving along `ray` that reaches the camera.
+        let mut throughput = Colour::WHITE;
+        // If `ray` was scattered from a non-specular surface, the probability density of it
+        // having been chosen, to weigh lights it finds against those found by shadow rays.
+        let mut scatter_pdf: Option<f64> = None;
+
+        for bounce in 0..rt.bounce_depth {
+            let c = match rt.collide(ray, (T_MIN, f64::INFINITY)) {
+                Some(c) => c,
+                None => {
+                    radiance += throughput.scale(rt.background.radiance(ray.dir));
+                    break;
+                }
+            };
+
+            let mut emitted = c.emitted();
+            if let Some(pdf) = scatter_pdf {
+                if !rt.lights.is_empty() && emitted != Colour::BLACK {
+                    emitted *= power_heuristic(pdf, self.light_pdf(rt, ray, c.t));
+                }
+            }
+            radiance += throughput.scale(emitted);
+
+            let sample = match c.material.sample(&c, -ray.dir, rng) {
+                Some(sample) => sample,
+                None => break,
+            };
+            scatter_pdf = Some(sample.pdf).filter(|_| !c.material.is_specular());
+            // The shadow ray stands in for the scattered ray finding a light, so it can only be
+            // sent if the scattered ray has bounces left to find one.
+            if scatter_pdf.is_some() && bounce + 1 < rt.bounce_depth {
+                radiance += throughput.scale(self.direct_light(rt, ray, &c, rng));
+            }
+
+            throughput = throughput.scale(sample.weight);
+            if bounce + 1 >= self.roulette_depth {
+                let survival = throughput.r.max(throughput.g).max(throughput.b).min(1.0);
+                if rng.gen::<f64>() >= survival {
+                    break;
+                }
+                throughput /= survival;
+            }
+            ray = Ray::new(c.point, sample.dir)
+                .with_time(ray.time)
+                .with_random(rng.gen());
+        }
+
+        radiance
+    }
+}
+
+impl PathTracer {
+    /// Light reaching the non-specular surface at `c` straight from one of the lights, chosen at
+    /// random, weighted against the chance of the surface scattering towards the same spot.
+    fn direct_light(&self, rt: &Raytracer, r: Ray, c: &Collision, rng: &mut dyn RngCore) -> Colour {
+        if rt.lights.is_empty() {
+            return Colour::BLACK;
+        }
+        let light = &rt.lights[rng.gen_range(0..rt.lights.len())];
+        let sample = match light.sample(c.point, rng) {
+            Some(sample) => sample,
+            None => return Colour::BLACK,
+        };
+        let response = c.material.eval(c, -r.dir, sample.dir);
+        if response == Colour::BLACK {
+            return Colour::BLACK;
+        }
+        let scatter_pdf = c.material.pdf(c, -r.dir, sample.dir);
+
+        // Only the light itself may be hit, at (very nearly) the distance it was sampled at.
+        let shadow = Ray::new(c.point, sample.dir)
+            .with_time(r.time)
+            .with_random(rng.gen());
+        let far = sample.distance * (1.0 + LIGHT_EPSILON);
+        let radiance = match rt.collide(shadow, (T_MIN, far)) {
+            Some(hit) if hit.t >= sample.distance * (1.0 - LIGHT_EPSILON) => hit.emitted(),
+            _ => return Colour::BLACK,
+        };
+
+        let light_pdf = sample.pdf / rt.lights.len() as f64;
+        response.scale(radiance) * (power_heuristic(light_pdf, scatter_pdf) / light_pdf)
+    }
+
+    /// Probability density of a shadow ray from the origin of `r` being sent along it, to a
+    /// light which it hits at `t`.
+    fn light_pdf(&self, rt: &Raytracer, r: Ray, t: f64) -> f64 {
+        let t_range = (t * (1.0 - LIGHT_EPSILON), t * (1.0 + LIGHT_EPSILON));
+        let total: f64 = rt.lights.iter().map(|l| l.pdf(r, t_range)).sum();
+        total / rt.lights.len() as f64
+    }
+}
+
+/// Relative tolerance when checking that a ray hits a light at the expected distance.
+const LIGHT_EPSILON: f64 = 1e-6;
+
+/// Weight for a sample taken with probability density `pdf`, when another strategy could have
+/// taken the same sample with density `other`.
+fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other * other);
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
+    }
+}
+
+/// Shows the shading normal of the first surface hit, with each axis mapped from -1..1 to
+/// 0..1 in red, green and blue. Misses are black.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn radiance(&self, rt: &Raytracer, ray: Ray, _rng: &mut dyn RngCore) -> Colour {
+        match rt.collide(ray, (T_MIN, f64::INFINITY)) {
+            Some(c) => Colour::new(c.normal.x + 1.0, c.normal.y + 1.0, c.normal.z + 1.0) / 2,
+            None => Colour::BLACK,
+        }
+    }
+}
+
+/// Shows the distance to the first surface hit, from black up close to white at `far` and
+/// beyond. Misses are white.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Depth {
+    pub far: f64,
+}
+
+impl Depth {
+    pub fn new(far: f64) -> Self {
+        Depth { far }
+    }
+}
+
+impl Integrator for Depth {
+    fn radiance(&self, rt: &Raytracer, ray: Ray, _rng: &mut dyn RngCore) -> Colour {
+        let distance = match rt.collide(ray, (T_MIN, f64::INFINITY)) {
+            Some(c) => c.t * ray.dir.length(),
+            None => f64::INFINITY,
+        };
+        Colour::WHITE * (distance / self.far).min(1.0)
+    }
+}
+
+/// Shows the colour of the first surface hit: the weight its material gives a scattered ray,
+/// or black if it scatters nothing. Misses show the background.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Albedo;
+
+impl Integrator for Albedo {
+    fn radiance(&self, rt: &Raytracer, ray: Ray, rng: &mut dyn RngCore) -> Colour {
+        match rt.collide(ray, (T_MIN, f64::INFINITY)) {
+            Some(c) => c
+                .material
+                .sample(&c, -ray.dir, rng)
+                .map_or(Colour::BLACK, |s| s.weight),
+            None => rt.background.radiance(ray.dir),
+        }
+    }
+}
+
+/// Shows how much of the sky each surface can see, by sending one ray from the first surface
+/// hit in a cosine-weighted direction and checking whether anything lies within `distance`.
+/// Misses are white.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct AmbientOcclusion {
+    pub distance: f64,
+}
+
+impl AmbientOcclusion {
+    pub fn new(distance: f64) -> Self {
+        AmbientOcclusion { distance }
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, rt: &Raytracer, ray: Ray, rng: &mut dyn RngCore) -> Colour {
+        let c = match rt.collide(ray, (T_MIN, f64::INFINITY)) {
+            Some(c) => c,
+            None => return Colour::WHITE,
+        };
+        let dir = c.normal + Vec3::random_unit(rng);
+        if dir.small() {
+            return Colour::WHITE;
+        }
+
+        let probe = Ray::new(c.point, dir.unit())
+            .with_time(ray.time)
+            .with_random(rng.gen());
+        match rt.collide(probe, (T_MIN, self.distance)) {
+            Some(_) => Colour::BLACK,
+            None => Colour::WHITE,
+        }
+    }
+}
+
+/// Shows how many times paths bounce before they leave the scene, are absorbed or run out of
+/// bounces, scattering as the path tracer does. Shades from blue for no bounces through green
+/// to red for `bounce_depth`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct BounceHeatmap;
+
+impl Integrator for BounceHeatmap {
+    fn radiance(&self, rt: &Raytracer, mut ray: Ray, rng: &mut dyn RngCore) -> Colour {
+        let mut bounces = 0;
+        while bounces < rt.bounce_depth {
+            let c = match rt.collide(ray, (T_MIN, f64::INFINITY)) {
+                Some(c) => c,
+                None => break,
+            };
+            match c.material.sample(&c, -ray.dir, rng) {
+                Some(s) => {
+                    ray = Ray::new(c.point, s.dir)
+                        .with_time(ray.time)
+                        .with_random(rng.gen())
+                }
+                None => break,
+            }
+            bounces += 1;
+        }
+
+        heatmap(bounces as f64 / rt.bounce_depth.max(1) as f64)
+    }
+}
+
+/// A colour from blue for 0, through green, to red for 1.
+pub(crate) fn heatmap(heat: f64) -> Colour {
+    let (blue, green, red) = (
+        Colour::new(0.0, 0.0, 1.0),
+        Colour::new(0.0, 1.0, 0.0),
+        Colour::new(1.0, 0.0, 0.0),
+    );
+    if heat < 0.5 {
+        Blend(blue, green).at(heat * 2.0)
+    } else {
+        Blend(green, red).at(heat * 2.0 - 1.0)
+    }
+}
@@ -1,17 +1,53 @@
+pub use aabb::*;
+pub use background::*;
+pub use bvh::*;
 pub use camera::*;
+pub use checkpoint::*;
 pub use collider::*;
 pub use colour::*;
+pub use distributed::*;
 pub use encode::*;
+pub use instance::*;
+pub use integrator::*;
+pub use light::*;
 pub use material::*;
+pub use medium::*;
+pub use mesh::*;
+pub use obj::*;
+pub use progress::*;
 pub use ray::*;
 pub use raytracer::*;
+pub use scene_file::*;
+pub use scenes::*;
+pub use texture::*;
+pub use tile::*;
+pub use tonemap::*;
+pub use transform::*;
 pub use vec3::*;
 
+mod aabb;
+mod background;
+mod bvh;
 mod camera;
+mod checkpoint;
 mod collider;
 mod colour;
+mod distributed;
 mod encode;
+mod instance;
+mod integrator;
+mod light;
 mod material;
+mod medium;
+mod mesh;
+mod obj;
+mod progress;
 mod ray;
 mod raytracer;
+mod scene_file;
+mod scenes;
+mod texture;
+mod tile;
+mod tonemap;
+mod transform;
 mod vec3;
@@ -0,0 +1,114 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use rand::{Rng, RngCore};
+
+use crate::{Collider, Material, Ray, Sphere, Vec3};
+
+/// A direction from some point towards a light, chosen by [`Light::sample`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LightSample {
+    /// Unit vector from the point towards the chosen spot on the light.
+    pub dir: Vec3,
+    /// How far away the chosen spot is.
+    pub distance: f64,
+    /// Probability density of choosing `dir`, per unit solid angle.
+    pub pdf: f64,
+}
+
+/// A collider which gives off light and can be aimed at directly, so that the renderer can send
+/// shadow rays to it rather than waiting for scattered rays to find it by chance.
+pub trait Light: Collider {
+    /// Choose a direction from `origin` towards the light, or `None` if there's none to choose.
+    fn sample(&self, origin: Vec3, rng: &mut dyn RngCore) -> Option<LightSample>;
+
+    /// The probability density per unit solid angle of [`sample`](Light::sample) choosing the
+    /// direction of `ray` from its origin, which is zero unless the ray hits the light within
+    /// `t_range`.
+    fn pdf(&self, ray: Ray, t_range: (f64, f64)) -> f64;
+}
+
+/// The lights in a scene, which must also be among its colliders.
+pub type Lights = Vec<Arc<dyn Light + Send + Sync>>;
+
+/// Two unit vectors perpendicular to the unit vector `w` and to each other.
+pub(crate) fn basis(w: Vec3) -> (Vec3, Vec3) {
+    let a = if w.x.abs() > 0.9 {
+        Vec3::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let v = w.cross(a).unit();
+    (w.cross(v), v)
+}
+
+/// A point chosen uniformly from the surface of a triangle.
+pub(crate) fn sample_triangle([a, b, c]: [Vec3; 3], rng: &mut dyn RngCore) -> Vec3 {
+    let su = rng.gen::<f64>().sqrt();
+    let v = rng.gen::<f64>();
+    a * (1.0 - su) + b * (su * (1.0 - v)) + c * (su * v)
+}
+
+/// Convert a density per unit area at `point` on a surface facing `normal` into a density per
+/// unit solid angle seen from `origin`.
+pub(crate) fn to_solid_angle(
+    area_pdf: f64,
+    origin: Vec3,
+    point: Vec3,
+    normal: Vec3,
+) -> Option<LightSample> {
+    let offset = point - origin;
+    let distance = offset.length();
+    let dir = offset / distance;
+    let cos = normal.unit().dot(dir).abs();
+    if cos < 1e-9 || distance < 1e-9 {
+        return None;
+    }
+
+    Some(LightSample {
+        dir,
+        distance,
+        pdf: area_pdf * distance.powi(2) / cos,
+    })
+}
+
+impl<M: Material> Sphere<M> {
+    /// One minus the cosine of the half-angle of the cone the sphere fills as seen from
+    /// `origin`, or `None` if `origin` is inside it.
+    fn cone(&self, origin: Vec3) -> Option<f64> {
+        let sin2 = self.radius.powi(2) / (self.centre - origin).squared();
+        if sin2 >= 1.0 {
+            return None;
+        }
+        // Rearranged from 1 - sqrt(1 - sin²) to stay accurate for small, distant spheres.
+        Some(sin2 / (1.0 + (1.0 - sin2).sqrt()))
+    }
+}
+
+impl<M: Material> Light for Sphere<M> {
+    /// Chooses uniformly from the cone of directions which hit the sphere.
+    fn sample(&self, origin: Vec3, rng: &mut dyn RngCore) -> Option<LightSample> {
+        let one_minus_cos_max = self.cone(origin)?;
+        let w = (self.centre - origin).unit();
+        let (u, v) = basis(w);
+
+        let one_minus_cos = rng.gen::<f64>() * one_minus_cos_max;
+        let cos = 1.0 - one_minus_cos;
+        let sin = (one_minus_cos * (2.0 - one_minus_cos)).sqrt();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+        let dir = u * (sin * phi.cos()) + v * (sin * phi.sin()) + w * cos;
+
+        let hit = self.collide(Ray::new(origin, dir), (0.0, f64::INFINITY))?;
+        Some(LightSample {
+            dir,
+            distance: hit.t,
+            pdf: (2.0 * PI * one_minus_cos_max).recip(),
+        })
+    }
+
+    fn pdf(&self, ray: Ray, t_range: (f64, f64)) -> f64 {
+        match (self.cone(ray.orig), self.collide(ray, t_range)) {
+            (Some(one_minus_cos_max), Some(_)) => (2.0 * PI * one_minus_cos_max).recip(),
+            _ => 0.0,
+        }
+    }
+}
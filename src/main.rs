@@ -1,116 +1,716 @@
-use std::{fs::File, io, sync::Arc};
+use std::{
+    error::Error,
+    fs::{self, File},
+    io,
+    net::{TcpListener, TcpStream},
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    process,
+    sync::Arc,
+    thread,
+    time::Duration,
+};
 
-use itertools::iproduct;
-use rand::{random, thread_rng, Rng};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+use rand::{rngs::StdRng, SeedableRng};
 
 use rez::{
-    encode_webp, Camera, Collider, Colour, Dielectric, Lambertian, Metal, Raytracer, Sphere, Vec3,
+    coordinate, cornell_box, encode_exr_with, encode_hdr, encode_png_with, encode_ppm, encode_webp,
+    load_scene, random_scene, scene_id, work, Accumulation, Adaptive, Albedo, AmbientOcclusion,
+    BitDepth, BounceHeatmap, Bvh, Camera, Collider, Colour, Depth, DisplayTransform,
+    ExrCompression, ExrOptions, Normals, PathTracer, PngOptions, Precision, Progress, Raytracer,
+    Region, TileOrder, ToneMap, Transfer, Vec3,
 };
 
-fn main() -> io::Result<()> {
-    let args = std::env::args().collect::<Vec<_>>();
-    let (stdout, mut lock, mut file);
-    let out: &mut dyn io::Write = if args.len() >= 2 && &args[1] != "-" {
-        file = File::create(&args[1])?;
-        &mut file
-    } else {
-        stdout = io::stdout();
-        lock = stdout.lock();
-        &mut lock
+#[derive(Parser)]
+#[command(name = "rez", version, about = "A physically based path tracer")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a scene to an image
+    Render(Box<RenderArgs>),
+    /// Check a scene file for errors without rendering it
+    Check {
+        /// Scene description file
+        scene: PathBuf,
+    },
+    /// Render tiles for a coordinator started with `rez render --listen`, loading the scene
+    /// the same way it did
+    Worker {
+        /// Address the coordinator is listening on, e.g. 127.0.0.1:7878
+        coordinator: String,
+        /// Number of threads to render with [default: one per CPU]
+        #[arg(short = 'j', long)]
+        threads: Option<usize>,
+    },
+}
+
+#[derive(Args)]
+struct RenderArgs {
+    /// Where to write the image, or `-` for stdout
+    output: String,
+
+    /// Scene description file to render
+    #[arg(short, long, conflicts_with = "builtin")]
+    scene: Option<PathBuf>,
+    /// Built-in scene to render when no scene file is given
+    #[arg(short, long, value_enum, default_value_t = Builtin::Random)]
+    builtin: Builtin,
+
+    /// Image width in pixels [default: from the height and the scene's aspect ratio]. With
+    /// --height too, the two must keep the scene's aspect ratio
+    #[arg(short = 'W', long)]
+    width: Option<u32>,
+    /// Image height in pixels [default: from the width and the scene's aspect ratio]
+    #[arg(short = 'H', long)]
+    height: Option<u32>,
+    /// Only render this part of the image, as `X,Y,WIDTH,HEIGHT` in pixels from the top left,
+    /// to redo it with more samples and paste it back in
+    #[arg(long, value_name = "X,Y,WIDTH,HEIGHT", value_parser = parse_region)]
+    region: Option<Region>,
+    /// Samples per pixel, or the most any pixel takes with --noise
+    #[arg(short = 'n', long)]
+    samples: Option<u32>,
+    /// Sample each pixel adaptively until the standard error of its brightness is at most this
+    /// fraction of it, e.g. 0.01
+    #[arg(long, value_name = "THRESHOLD")]
+    noise: Option<f64>,
+    /// Samples every pixel takes before --noise can stop it
+    #[arg(long, value_name = "SAMPLES", requires = "noise", default_value_t = 16)]
+    min_samples: u32,
+    /// Also write an image of how many samples each pixel took, from blue for none to red for
+    /// the most allowed
+    #[arg(long, value_name = "FILE")]
+    sample_map: Option<PathBuf>,
+    /// Maximum number of bounces per ray
+    #[arg(short, long)]
+    depth: Option<u32>,
+    /// Encode with this gamma rather than the sRGB curve [default: 1 for debugging
+    /// integrators]
+    #[arg(short, long)]
+    gamma: Option<f64>,
+    /// Brighten or darken the image by this many stops before tone mapping, for formats which
+    /// aren't linear
+    #[arg(long, value_name = "EV", allow_negative_numbers = true)]
+    exposure: Option<f64>,
+    /// How to bring bright highlights into range, for formats which aren't linear [default:
+    /// clamp]
+    #[arg(long, value_enum)]
+    tone_map: Option<ToneMapKind>,
+    /// Luminance shown as white by the extended Reinhard tone map
+    #[arg(long, value_name = "LUMINANCE", default_value_t = 4.0)]
+    white: f64,
+    /// How to work out what each camera ray sees
+    #[arg(short, long, value_enum, default_value_t = IntegratorKind::Path)]
+    integrator: IntegratorKind,
+    /// Distance shown as white by the depth integrator, and how far ambient occlusion looks
+    /// for occluders [default: the size of the scene]
+    #[arg(long)]
+    distance: Option<f64>,
+
+    /// Image format [default: from the output file's extension]
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+    /// Bits per channel, for formats which support more than one [default: 8 for PNG, 16 for
+    /// OpenEXR]
+    #[arg(long, value_enum)]
+    bits: Option<Bits>,
+    /// Seed for the random numbers used while rendering, which also lays out the random built-in
+    /// scene [default: the scene file's seed, or 0, with a new random layout each run]
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Render in passes of this many samples per pixel, rewriting the output after each one so
+    /// that long renders can be checked on as they go
+    #[arg(long, value_name = "SAMPLES")]
+    pass: Option<u32>,
+    /// Save the render so far to this file after every pass, so that it can be resumed if
+    /// stopped [default pass: 16 samples]
+    #[arg(long, value_name = "FILE")]
+    checkpoint: Option<PathBuf>,
+    /// Carry on from the --checkpoint file, if it exists, rather than starting again
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+    /// Width and height of the squares of pixels each thread renders at a time
+    #[arg(long, value_name = "PIXELS", default_value_t = 16)]
+    tile_size: u32,
+    /// Order to render tiles in
+    #[arg(long, value_enum, default_value_t = Order::Scanline)]
+    tile_order: Order,
+    /// Number of threads to render with [default: one per CPU]
+    #[arg(short = 'j', long)]
+    threads: Option<usize>,
+    /// Don't render here, but hand out tiles to `rez worker` processes connecting to this
+    /// address, which must be able to load the same scene file
+    #[arg(long, value_name = "ADDRESS", conflicts_with_all = ["pass", "checkpoint"])]
+    listen: Option<String>,
+    /// Width and height of the tiles handed to each worker
+    #[arg(long, value_name = "PIXELS", requires = "listen", default_value_t = 64)]
+    job_size: u32,
+    /// Don't show a progress bar
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Builtin {
+    /// Spheres of random materials scattered around three big ones
+    Random,
+    /// The Cornell box, lit only by its ceiling
+    Cornell,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum IntegratorKind {
+    /// Physically based path tracing
+    Path,
+    /// Surface normals as colours
+    Normals,
+    /// Distance from the camera, from black to white
+    Depth,
+    /// Surface colour, without lighting
+    Albedo,
+    /// Ambient occlusion, with nearby surfaces darkening each other
+    Ao,
+    /// Number of bounces each path takes, from blue to red
+    Bounces,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Order {
+    /// Rows from the top
+    Scanline,
+    /// Outwards from the middle
+    Spiral,
+    /// Along a Hilbert curve, keeping each tile next to the last
+    Hilbert,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum ToneMapKind {
+    /// Clip anything brighter than white
+    Clamp,
+    /// Reinhard's operator, which never quite reaches white
+    Reinhard,
+    /// Reinhard's operator, reaching white at --white
+    ExtendedReinhard,
+    /// ACES filmic
+    Aces,
+    /// John Hable's filmic curve from Uncharted 2
+    Hable,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// ASCII Netpbm
+    Ppm,
+    /// Lossless WebP
+    Webp,
+    /// PNG, 8 or 16 bits per channel
+    Png,
+    /// OpenEXR with ZIP compression, linear 16 or 32 bit float
+    Exr,
+    /// Radiance RGBE, linear
+    Hdr,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Bits {
+    #[value(name = "8")]
+    Eight,
+    #[value(name = "16")]
+    Sixteen,
+    #[value(name = "32")]
+    ThirtyTwo,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Option<Format> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        Format::from_str(&ext, true).ok()
+    }
+
+    /// Formats which store linear radiance rather than gamma corrected colour.
+    fn linear(self) -> bool {
+        matches!(self, Format::Exr | Format::Hdr)
+    }
+
+    /// The bit depth to encode with, checking it's one this format supports.
+    fn bits(self, bits: Option<Bits>) -> Result<Bits, String> {
+        let (default, allowed): (_, &[_]) = match self {
+            Format::Png => (Bits::Eight, &[Bits::Eight, Bits::Sixteen]),
+            Format::Exr => (Bits::Sixteen, &[Bits::Sixteen, Bits::ThirtyTwo]),
+            Format::Ppm | Format::Webp | Format::Hdr => (Bits::Eight, &[]),
+        };
+        match bits {
+            None => Ok(default),
+            Some(bits) if allowed.contains(&bits) => Ok(bits),
+            Some(bits) => Err(format!(
+                "{} images can't have {} bits per channel",
+                self.to_possible_value().unwrap().get_name(),
+                bits.to_possible_value().unwrap().get_name()
+            )),
+        }
+    }
+
+    fn encode(
+        self,
+        pixels: &[Colour],
+        width: u32,
+        height: u32,
+        bits: Bits,
+        out: impl io::Write,
+    ) -> io::Result<()> {
+        match self {
+            Format::Ppm => encode_ppm(pixels, width, height, out),
+            Format::Webp => encode_webp(pixels, width, height, out),
+            Format::Png => {
+                let options = PngOptions {
+                    bit_depth: if bits == Bits::Sixteen {
+                        BitDepth::Sixteen
+                    } else {
+                        BitDepth::Eight
+                    },
+                    alpha: false,
+                };
+                encode_png_with(pixels, width, height, out, options)
+            }
+            Format::Exr => {
+                let options = ExrOptions {
+                    precision: if bits == Bits::ThirtyTwo {
+                        Precision::Full
+                    } else {
+                        Precision::Half
+                    },
+                    compression: ExrCompression::Zip,
+                };
+                encode_exr_with(pixels, width, height, out, options)
+            }
+            Format::Hdr => encode_hdr(pixels, width, height, out),
+        }
+    }
+}
+
+/// Progress drawn as a bar on stderr.
+struct Bar(ProgressBar);
+
+impl Bar {
+    fn new() -> Self {
+        let bar = ProgressBar::new(0);
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template("{percent:>3}%▕{bar:40}▏ [{eta}/{elapsed}, {per_sec}]")
+                .progress_chars("█▉▊▋▌▍▎▏ "),
+        );
+        Bar(bar)
+    }
+}
+
+impl Progress for Bar {
+    fn started(&self, pixels: u64) {
+        self.0.set_length(pixels);
+        self.0.set_draw_delta(pixels / 1000);
+        self.0.reset();
+    }
+
+    fn advanced(&self, pixels: u64, _samples: u64) {
+        self.0.inc(pixels);
+    }
+
+    fn finished(&self) {
+        // Leave the bar where it got to, rather than filling it.
+        self.0.abandon();
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Render(args) => render(*args),
+        Command::Check { scene } => check(&scene),
+        Command::Worker {
+            coordinator,
+            threads,
+        } => worker(&coordinator, threads),
     };
 
-    // Image
-    const RATIO: f64 = 3.0 / 2.0;
-    const IMAGE_HEIGHT: u32 = 100; //
-    const IMAGE_WIDTH: u32 = (IMAGE_HEIGHT as f64 * RATIO) as u32;
-    const NUM_SAMPLES: u32 = 100;
-    const MAX_DEPTH: u32 = 50;
-
-    // World
-    let world = Arc::new(random_scene());
-
-    // Camera
-    let cam = Camera::builder()
-        .origin(Vec3::new(13.0, 2.0, 3.0))
-        .target(Vec3::new(0.0, 0.0, 0.0))
-        .vup(Vec3::new(0.0, 1.0, 0.0))
-        .v_fov(f64::to_radians(20.0))
-        .aspect_ratio(RATIO)
-        .aperture(0.0)
-        .focus_dist(10.0)
-        .build()
-        .unwrap();
-
-    let r = Raytracer::new(
-        world,
-        cam,
-        IMAGE_WIDTH,
-        IMAGE_HEIGHT,
-        NUM_SAMPLES,
-        MAX_DEPTH,
-    );
-
-    let pixels = r.render();
-
-    encode_webp(&pixels, IMAGE_WIDTH, IMAGE_HEIGHT, out)
-}
-
-fn random_scene() -> Vec<Box<dyn Collider + Send + Sync>> {
-    let mut world: Vec<Box<dyn Collider + Send + Sync>> = Vec::new();
-
-    // Ground
-    let ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
-    world.push(Box::new(Sphere::new(
-        Vec3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        ground,
-    )));
-
-    let glass = Arc::new(Dielectric::new(1.5));
-
-    for (a, b) in iproduct!((-10..=10), (-10..=10)) {
-        let centre = Vec3::new(
-            a as f64 + 0.9 * random::<f64>(),
-            0.2,
-            b as f64 + 0.9 * random::<f64>(),
+    if let Err(e) = result {
+        eprintln!("rez: {}", e);
+        process::exit(1);
+    }
+}
+
+fn render(args: RenderArgs) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let format = match (args.format, args.output.as_str()) {
+        (Some(format), _) => format,
+        (None, "-") => return Err("--format is needed to write to stdout".into()),
+        (None, output) => Format::from_path(Path::new(output)).ok_or_else(|| {
+            format!(
+                "can't tell the image format from `{}`, use --format to choose one",
+                output
+            )
+        })?,
+    };
+    let bits = format.bits(args.bits)?;
+    let sample_map = match &args.sample_map {
+        Some(path) => {
+            let format = Format::from_path(path).ok_or_else(|| {
+                format!(
+                    "can't tell the image format from `{}`, give it an extension",
+                    path.display()
+                )
+            })?;
+            Some((path, format, format.bits(None)?))
+        }
+        None => None,
+    };
+    if args.pass.is_some() && args.output == "-" {
+        return Err("--pass needs an output file to rewrite".into());
+    }
+
+    if args.listen.is_some()
+        && args.scene.is_none()
+        && args.builtin == Builtin::Random
+        && args.seed.is_none()
+    {
+        return Err(
+            "--listen needs a --seed, so that workers lay out the random scene the same".into(),
         );
+    }
+
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+
+    let mut r = raytracer(&args)?;
+    if !args.quiet {
+        r.progress = Arc::new(Bar::new());
+    }
+
+    let write = |image: &Accumulation| -> io::Result<()> {
+        let pixels = if format.linear() {
+            image.linear()
+        } else {
+            image.resolve(&r.display)
+        };
+        if args.output == "-" {
+            let stdout = io::stdout();
+            format.encode(&pixels, image.width(), image.height(), bits, stdout.lock())
+        } else {
+            let file = File::create(&args.output)?;
+            let out = io::BufWriter::new(file);
+            format.encode(&pixels, image.width(), image.height(), bits, out)
+        }
+    };
+
+    let write_sample_map = |image: &Accumulation| -> io::Result<()> {
+        if let Some((path, format, bits)) = &sample_map {
+            let out = io::BufWriter::new(File::create(path)?);
+            let (width, height) = (image.width(), image.height());
+            format.encode(&image.sample_map(), width, height, *bits, out)?;
+        }
+        Ok(())
+    };
+
+    let save = |image: &Accumulation, path: &Path| -> io::Result<()> {
+        // Write alongside and then replace, so that being stopped part way through saving
+        // doesn't lose the last checkpoint.
+        let partial = path.with_extension("partial");
+        r.checkpoint(image, io::BufWriter::new(File::create(&partial)?))?;
+        fs::rename(&partial, path)
+    };
+
+    if let Some(address) = &args.listen {
+        let listener = TcpListener::bind(address)?;
+        eprintln!("rez: listening on {}", listener.local_addr()?);
+        let setup: Vec<String> = std::env::args().collect();
+        let image = coordinate(&r, listener, &setup, args.job_size.max(1))?;
+        write(&image)?;
+        write_sample_map(&image)?;
+        return Ok(());
+    }
+
+    let start = match &args.checkpoint {
+        Some(path) if args.resume && path.exists() => {
+            let file = File::open(path)?;
+            r.resume(io::BufReader::new(file))
+                .map_err(|e| format!("{}: {}", path.display(), e))?
+        }
+        _ => {
+            let frame = r.frame();
+            Accumulation::new(frame.width, frame.height)
+        }
+    };
+    let pass = match (args.pass, &args.checkpoint) {
+        (Some(pass), _) => pass,
+        (None, Some(_)) => 16,
+        (None, None) => r.samples_per_pixel,
+    };
+
+    let mut result = Ok(());
+    let image = r.render_from(start, pass, |image| {
+        let saved = match &args.checkpoint {
+            Some(path) => save(image, path),
+            None => Ok(()),
+        };
+        let written = match args.pass {
+            Some(_) => write(image),
+            None => Ok(()),
+        };
+        match saved.and(written) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => {
+                result = Err(e);
+                ControlFlow::Break(())
+            }
+        }
+    });
+    result?;
+    if args.pass.is_none() {
+        write(&image)?;
+    }
+
+    write_sample_map(&image)?;
+    Ok(())
+}
 
-        if (centre - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-            match random::<f64>() {
-                r if (0.0..0.8).contains(&r) => {
-                    let albedo = random();
-                    let mat = Arc::new(Lambertian::new(albedo));
-                    world.push(Box::new(Sphere::new(centre, 0.2, mat)));
-                }
-                r if (0.8..0.95).contains(&r) => {
-                    let albedo = random();
-                    let fuzz = thread_rng().gen_range(0.0..0.5);
-                    let mat = Arc::new(Metal::new(albedo, fuzz));
-                    world.push(Box::new(Sphere::new(centre, 0.2, mat)));
-                }
-                r if (0.95..1.0).contains(&r) => {
-                    world.push(Box::new(Sphere::new(centre, 0.2, glass.clone())))
-                }
-                _ => unreachable!(),
-            };
-        }
-    }
-
-    world.push(Box::new(Sphere::new(
-        Vec3::new(0.0, 1.0, 0.0),
-        1.0,
-        Arc::new(Dielectric::new(1.5)),
-    )));
-    world.push(Box::new(Sphere::new(
-        Vec3::new(-4.0, 1.0, 0.0),
-        1.0,
-        Arc::new(Lambertian::new(Colour::new(0.4, 0.2, 0.1))),
-    )));
-    world.push(Box::new(Sphere::new(
-        Vec3::new(4.0, 1.0, 0.0),
-        1.0,
-        Arc::new(Metal::new(Colour::new(0.7, 0.6, 0.5), 0.0)),
-    )));
-
-    world
+/// Set up the raytracer the render arguments describe.
+fn raytracer(args: &RenderArgs) -> Result<Raytracer, Box<dyn Error + Send + Sync>> {
+    let mut r = match &args.scene {
+        Some(path) => {
+            let (r, warnings) = load_scene(path)?;
+            for warning in warnings {
+                eprintln!("warning: {}", warning);
+            }
+            r
+        }
+        None => builtin(args.builtin, args.seed),
+    };
+
+    let ratio = r.width as f64 / r.height as f64;
+    match (args.width, args.height) {
+        (Some(width), Some(height)) => {
+            // The camera is built for the scene's shape, so any other would stretch the image.
+            let rounded = |v: f64| v.round() as u32;
+            if height != rounded(width as f64 / ratio) && width != rounded(height as f64 * ratio) {
+                return Err(format!(
+                    "a {}x{} image doesn't match the scene's aspect ratio of {:.3}, \
+                     give only --width or --height",
+                    width, height, ratio
+                )
+                .into());
+            }
+            r.width = width;
+            r.height = height;
+        }
+        (Some(width), None) => {
+            r.width = width;
+            r.height = (width as f64 / ratio).round() as u32;
+        }
+        (None, Some(height)) => {
+            r.width = (height as f64 * ratio).round() as u32;
+            r.height = height;
+        }
+        (None, None) => {}
+    }
+    if r.width < 2 || r.height < 2 {
+        return Err("the image must be at least 2 pixels wide and high".into());
+    }
+    if let Some(region) = args.region {
+        if !region.fits(r.width, r.height) {
+            return Err(
+                format!("--region must be inside the {}x{} image", r.width, r.height).into(),
+            );
+        }
+        r.region = Some(region);
+    }
+    r.tile_size = args.tile_size.max(1);
+    r.tile_order = match args.tile_order {
+        Order::Scanline => TileOrder::Scanline,
+        Order::Spiral => TileOrder::Spiral,
+        Order::Hilbert => TileOrder::Hilbert,
+    };
+    if let Some(samples) = args.samples {
+        if samples == 0 {
+            return Err("--samples must be at least 1".into());
+        }
+        r.samples_per_pixel = samples;
+    }
+    if let Some(threshold) = args.noise {
+        if !(threshold > 0.0 && threshold.is_finite()) {
+            return Err("--noise must be positive".into());
+        }
+        r.adaptive = Some(Adaptive {
+            threshold,
+            min_samples: args.min_samples,
+        });
+    }
+    if let Some(depth) = args.depth {
+        r.bounce_depth = depth;
+    }
+    let distance = args.distance.unwrap_or_else(|| {
+        r.scene
+            .bounding_box()
+            .map_or(1000.0, |b| (b.max - b.min).length())
+    });
+    r.integrator = match args.integrator {
+        IntegratorKind::Path => Arc::new(PathTracer::default()),
+        IntegratorKind::Normals => Arc::new(Normals),
+        IntegratorKind::Depth => Arc::new(Depth::new(distance)),
+        IntegratorKind::Albedo => Arc::new(Albedo),
+        IntegratorKind::Ao => Arc::new(AmbientOcclusion::new(distance)),
+        IntegratorKind::Bounces => Arc::new(BounceHeatmap),
+    };
+    let integrator = args.integrator.to_possible_value().unwrap();
+    r.scene_id = r
+        .scene_id
+        .map(|id| scene_id(format!("{} {} {}", id, integrator.get_name(), distance)));
+    if args.integrator != IntegratorKind::Path {
+        r.display = DisplayTransform {
+            transfer: Transfer::Gamma(1.0),
+            ..DisplayTransform::default()
+        };
+    }
+    if let Some(gamma) = args.gamma {
+        if !(gamma > 0.0 && gamma.is_finite()) {
+            return Err("--gamma must be positive".into());
+        }
+        r.display.transfer = Transfer::Gamma(gamma);
+    }
+    if let Some(exposure) = args.exposure {
+        r.display.exposure = exposure;
+    }
+    if !(args.white > 0.0 && args.white.is_finite()) {
+        return Err("--white must be positive".into());
+    }
+    if let Some(tone_map) = args.tone_map {
+        r.display.tone_map = match tone_map {
+            ToneMapKind::Clamp => ToneMap::Clamp,
+            ToneMapKind::Reinhard => ToneMap::Reinhard,
+            ToneMapKind::ExtendedReinhard => ToneMap::ExtendedReinhard { white: args.white },
+            ToneMapKind::Aces => ToneMap::Aces,
+            ToneMapKind::Hable => ToneMap::Hable,
+        };
+    }
+    if let Some(seed) = args.seed {
+        r.seed = seed;
+    }
+    Ok(r)
+}
+
+fn parse_region(s: &str) -> Result<Region, String> {
+    let numbers = s
+        .split(',')
+        .map(|n| n.trim().parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    match numbers[..] {
+        [x, y, width, height] => Ok(Region::new(x, y, width, height)),
+        _ => Err("expected four numbers, `X,Y,WIDTH,HEIGHT`".to_string()),
+    }
+}
+
+fn worker(coordinator: &str, threads: Option<usize>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(threads) = threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+
+    // Give the coordinator a few seconds to start, so they can be launched together.
+    let mut tries = 0;
+    let stream = loop {
+        match TcpStream::connect(coordinator) {
+            Ok(stream) => break stream,
+            Err(_) if tries < 50 => {
+                tries += 1;
+                thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) => return Err(format!("can't connect to {}: {}", coordinator, e).into()),
+        }
+    };
+
+    work(stream, |setup| match Cli::try_parse_from(setup)?.command {
+        Command::Render(args) => raytracer(&args),
+        _ => Err("the coordinator isn't rendering".into()),
+    })?;
+    Ok(())
+}
+
+fn check(scene: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (_, warnings) = load_scene(scene)?;
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
+    }
+    eprintln!("{}: ok", scene.display());
+    Ok(())
+}
+
+fn builtin(scene: Builtin, seed: Option<u64>) -> Raytracer {
+    let layout = seed.unwrap_or_else(rand::random);
+    let mut rng = StdRng::seed_from_u64(layout);
+
+    let mut r = match scene {
+        Builtin::Random => {
+            const RATIO: f64 = 3.0 / 2.0;
+            const IMAGE_HEIGHT: u32 = 100;
+
+            let cam = Camera::builder()
+                .origin(Vec3::new(13.0, 2.0, 3.0))
+                .target(Vec3::new(0.0, 0.0, 0.0))
+                .vup(Vec3::new(0.0, 1.0, 0.0))
+                .v_fov(f64::to_radians(20.0))
+                .aspect_ratio(RATIO)
+                .aperture(0.0)
+                .focus_dist(10.0)
+                .build()
+                .unwrap();
+
+            Raytracer::new(
+                Arc::new(Bvh::new(random_scene(&mut rng))),
+                cam,
+                (IMAGE_HEIGHT as f64 * RATIO) as u32,
+                IMAGE_HEIGHT,
+                100,
+                50,
+            )
+        }
+        Builtin::Cornell => {
+            const IMAGE_SIZE: u32 = 300;
+
+            let cam = Camera::builder()
+                .origin(Vec3::new(278.0, 278.0, -800.0))
+                .target(Vec3::new(278.0, 278.0, 0.0))
+                .vup(Vec3::new(0.0, 1.0, 0.0))
+                .v_fov(f64::to_radians(40.0))
+                .aspect_ratio(1.0)
+                .aperture(0.0)
+                .focus_dist(10.0)
+                .build()
+                .unwrap();
+
+            let (world, lights) = cornell_box();
+            let mut r = Raytracer::new(
+                Arc::new(Bvh::new(world)),
+                cam,
+                IMAGE_SIZE,
+                IMAGE_SIZE,
+                200,
+                50,
+            );
+            r.lights = lights;
+            r.background = Arc::new(Colour::BLACK);
+            r
+        }
+    };
+    let name = scene.to_possible_value().unwrap();
+    r.scene_id = Some(scene_id(format!("{} {}", name.get_name(), layout)));
+    r
 }
@@ -1,8 +1,8 @@
-use std::ops::Neg;
+use std::{f64::consts::PI, ops::Neg, sync::Arc};
 
-use rand::random;
+use rand::{Rng, RngCore};
 
-use crate::{Collision, Colour, Ray, Vec3};
+use crate::{Collision, Colour, Ray, SharedTexture, Texture, Vec3};
 
 fn reflect(incident: Vec3, normal: Vec3) -> Vec3 {
     incident - normal * incident.dot(normal) * 2.0
@@ -15,68 +15,236 @@ fn refract(incident: Vec3, normal: Vec3, eta_ratio: f64) -> Vec3 {
     transmitted_perp + transmitted_para
 }
 
+/// A direction for light to scatter in, chosen by [`Material::sample`].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BsdfSample {
+    /// The direction the light arrives from, away from the surface.
+    pub dir: Vec3,
+    /// The BSDF times the cosine of the angle of incidence, divided by `pdf`: the factor by
+    /// which light arriving from `dir` contributes to the light leaving.
+    pub weight: Colour,
+    /// Probability density of choosing `dir`, per unit solid angle. For specular materials,
+    /// which can only choose between a few directions, the probability of choosing this one.
+    pub pdf: f64,
+}
+
+/// How a surface scatters light, in terms of its BSDF (bidirectional scattering distribution
+/// function).
+///
+/// Directions point away from the surface: `wo` back towards where the ray came from, and `wi`
+/// towards where the light it carries comes from. Neither need be a unit vector.
 pub trait Material {
-    fn scatter(&self, ray: Ray, collision: &Collision) -> Option<(Colour, Ray)>;
+    /// Choose a direction `wi` for light leaving along `wo` to have arrived from, drawing any
+    /// random numbers needed from `rng`. `None` if the surface absorbs the light.
+    fn sample(&self, collision: &Collision, wo: Vec3, rng: &mut dyn RngCore) -> Option<BsdfSample>;
+
+    /// The BSDF for light arriving from `wi` and leaving along `wo`, times the cosine of the
+    /// angle `wi` makes with the surface. Always black for specular materials.
+    fn eval(&self, _collision: &Collision, _wo: Vec3, _wi: Vec3) -> Colour {
+        Colour::BLACK
+    }
+
+    /// Probability density per unit solid angle of [`sample`](Material::sample) choosing `wi`
+    /// for light leaving along `wo`. Always zero for specular materials.
+    fn pdf(&self, _collision: &Collision, _wo: Vec3, _wi: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Whether the surface scatters light only in a few exact directions, like a mirror or
+    /// glass, so that [`eval`](Material::eval) and [`pdf`](Material::pdf) can't describe it
+    /// and it can only be lit by [`sample`](Material::sample).
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    /// Scatter `ray` off the surface, drawing any random numbers needed from `rng`.
+    fn scatter(
+        &self,
+        ray: Ray,
+        collision: &Collision,
+        rng: &mut dyn RngCore,
+    ) -> Option<(Colour, Ray)> {
+        let sample = self.sample(collision, -ray.dir, rng)?;
+        let scattered = Ray::new(collision.point, sample.dir)
+            .with_time(ray.time)
+            .with_random(rng.gen());
+        Some((sample.weight, scattered))
+    }
+
+    /// Radiance given off by the surface itself, independent of any light arriving at it.
+    fn emitted(&self, _collision: &Collision) -> Colour {
+        Colour::BLACK
+    }
 }
 
-impl<'a, M> Material for &'a M
+impl<M> Material for &M
 where
     M: Material,
 {
-    fn scatter(&self, ray: Ray, collision: &Collision) -> Option<(Colour, Ray)> {
-        (*self).scatter(ray, collision)
+    fn sample(&self, collision: &Collision, wo: Vec3, rng: &mut dyn RngCore) -> Option<BsdfSample> {
+        (*self).sample(collision, wo, rng)
+    }
+
+    fn eval(&self, collision: &Collision, wo: Vec3, wi: Vec3) -> Colour {
+        (*self).eval(collision, wo, wi)
+    }
+
+    fn pdf(&self, collision: &Collision, wo: Vec3, wi: Vec3) -> f64 {
+        (*self).pdf(collision, wo, wi)
+    }
+
+    fn is_specular(&self) -> bool {
+        (*self).is_specular()
+    }
+
+    fn emitted(&self, collision: &Collision) -> Colour {
+        (*self).emitted(collision)
+    }
+}
+
+impl<M> Material for Arc<M>
+where
+    M: Material + ?Sized,
+{
+    fn sample(&self, collision: &Collision, wo: Vec3, rng: &mut dyn RngCore) -> Option<BsdfSample> {
+        (**self).sample(collision, wo, rng)
+    }
+
+    fn eval(&self, collision: &Collision, wo: Vec3, wi: Vec3) -> Colour {
+        (**self).eval(collision, wo, wi)
+    }
+
+    fn pdf(&self, collision: &Collision, wo: Vec3, wi: Vec3) -> f64 {
+        (**self).pdf(collision, wo, wi)
+    }
+
+    fn is_specular(&self) -> bool {
+        (**self).is_specular()
+    }
+
+    fn emitted(&self, collision: &Collision) -> Colour {
+        (**self).emitted(collision)
     }
 }
 
 pub struct Lambertian {
-    albedo: Colour,
+    albedo: SharedTexture,
 }
 
 impl Lambertian {
     pub fn new(albedo: Colour) -> Self {
-        Lambertian { albedo }
+        Lambertian::textured(albedo)
+    }
+
+    /// A surface whose albedo varies across it.
+    pub fn textured(albedo: impl Texture + Send + Sync + 'static) -> Self {
+        Lambertian {
+            albedo: Arc::new(albedo),
+        }
     }
 }
 
+/// Picks uniformly from the hemisphere above the surface, and weights every direction by the
+/// albedo alone.
 impl Material for Lambertian {
-    fn scatter(&self, _ray: Ray, col: &Collision) -> Option<(Colour, Ray)> {
+    fn sample(&self, col: &Collision, _wo: Vec3, rng: &mut dyn RngCore) -> Option<BsdfSample> {
         let dir = loop {
-            let dir = random::<Vec3>().unit().ensure_in_hemisphere(col.normal);
+            let dir = rng.gen::<Vec3>().unit().ensure_in_hemisphere(col.normal);
             if !dir.small() {
                 break dir;
             }
         };
-        let scattered = Ray::new(col.point, dir);
-        let attenuation = self.albedo;
-        Some((attenuation, scattered))
+        Some(BsdfSample {
+            dir,
+            weight: self.albedo.value(col.uv, col.point),
+            pdf: (2.0 * PI).recip(),
+        })
+    }
+
+    fn eval(&self, col: &Collision, wo: Vec3, wi: Vec3) -> Colour {
+        self.albedo.value(col.uv, col.point) * self.pdf(col, wo, wi)
+    }
+
+    fn pdf(&self, col: &Collision, _wo: Vec3, wi: Vec3) -> f64 {
+        if wi.dot(col.normal) > 0.0 {
+            (2.0 * PI).recip()
+        } else {
+            0.0
+        }
     }
 }
 
 pub struct Metal {
-    albedo: Colour,
+    albedo: SharedTexture,
     fuzz: f64,
 }
 
 impl Metal {
     pub fn new(albedo: Colour, fuzz: f64) -> Self {
+        Metal::textured(albedo, fuzz)
+    }
+
+    /// A metal whose tint varies across it.
+    pub fn textured(albedo: impl Texture + Send + Sync + 'static, fuzz: f64) -> Self {
         Metal {
-            albedo,
+            albedo: Arc::new(albedo),
             fuzz: fuzz.clamp(0.0, 1.0),
         }
     }
 }
 
+/// Reflects like a mirror, then moves the reflected direction to a random point on a sphere of
+/// radius `fuzz` around its tip.
 impl Material for Metal {
-    fn scatter(&self, ray: Ray, col: &Collision) -> Option<(Colour, Ray)> {
-        let reflected = reflect(ray.dir, col.normal);
-        let dir = reflected + random::<Vec3>().unit() * self.fuzz;
-        let scattered = Ray::new(col.point, dir);
-        let attenuation = self.albedo;
-        if reflected.dot(col.normal) > 0.0 {
-            Some((attenuation, scattered))
+    fn sample(&self, col: &Collision, wo: Vec3, rng: &mut dyn RngCore) -> Option<BsdfSample> {
+        let reflected = reflect(-wo, col.normal);
+        let dir = reflected + rng.gen::<Vec3>().unit() * self.fuzz;
+        if reflected.dot(col.normal) <= 0.0 {
+            return None;
+        }
+
+        let pdf = if self.is_specular() {
+            1.0
         } else {
-            None
+            self.pdf(col, wo, dir)
+        };
+        Some(BsdfSample {
+            dir,
+            weight: self.albedo.value(col.uv, col.point),
+            pdf,
+        })
+    }
+
+    fn eval(&self, col: &Collision, wo: Vec3, wi: Vec3) -> Colour {
+        self.albedo.value(col.uv, col.point) * self.pdf(col, wo, wi)
+    }
+
+    fn pdf(&self, col: &Collision, wo: Vec3, wi: Vec3) -> f64 {
+        let reflected = reflect(-wo, col.normal);
+        if self.is_specular() || reflected.dot(col.normal) <= 0.0 {
+            return 0.0;
         }
+
+        // Directions from the origin along `wi` meet the fuzz sphere where
+        // t² - 2bt + (|reflected|² - fuzz²) = 0. Each crossing adds the sphere's uniform area
+        // density, 1 / 4πfuzz², converted to solid angle by t² / cos, where the cosine between
+        // `wi` and the sphere's normal there is √discriminant / fuzz.
+        let wi = wi.unit();
+        let b = wi.dot(reflected);
+        let discriminant = b * b - (reflected.squared() - self.fuzz.powi(2));
+        if discriminant <= 0.0 {
+            return 0.0;
+        }
+        let root = discriminant.sqrt();
+        [b - root, b + root]
+            .iter()
+            .filter(|&&t| t > 0.0)
+            .map(|t| t * t / (4.0 * PI * self.fuzz * root))
+            .sum()
+    }
+
+    fn is_specular(&self) -> bool {
+        self.fuzz == 0.0
     }
 }
 
@@ -91,7 +259,8 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: Ray, col: &Collision) -> Option<(Colour, Ray)> {
+    fn sample(&self, col: &Collision, wo: Vec3, rng: &mut dyn RngCore) -> Option<BsdfSample> {
+        let incident = -wo;
         // Are we outside the material?
         let eta_ratio = if col.front {
             self.eta.recip()
@@ -99,21 +268,33 @@ impl Material for Dielectric {
             self.eta
         };
 
-        let cos_theta = ray.dir.unit().neg().dot(col.normal);
+        let cos_theta = incident.unit().neg().dot(col.normal);
         let sin_theta = (1.0 - cos_theta.powi(2)).sqrt();
 
         let internal_reflection = eta_ratio * sin_theta > 1.0;
-        let other_reflection = reflectance(cos_theta, eta_ratio) > random::<f64>();
+        let reflect_chance = reflectance(cos_theta, eta_ratio);
+        let other_reflection = reflect_chance > rng.gen::<f64>();
 
-        let direction = if internal_reflection || other_reflection {
-            reflect(ray.dir, col.normal)
+        let (dir, pdf) = if internal_reflection {
+            (reflect(incident, col.normal), 1.0)
+        } else if other_reflection {
+            (reflect(incident, col.normal), reflect_chance)
         } else {
-            refract(ray.dir, col.normal, eta_ratio)
+            (
+                refract(incident, col.normal, eta_ratio),
+                1.0 - reflect_chance,
+            )
         };
 
-        let attenuation = Colour::new(1.0, 1.0, 1.0);
-        let scattered = Ray::new(col.point, direction);
-        Some((attenuation, scattered))
+        Some(BsdfSample {
+            dir,
+            weight: Colour::new(1.0, 1.0, 1.0),
+            pdf,
+        })
+    }
+
+    fn is_specular(&self) -> bool {
+        true
     }
 }
 
@@ -121,3 +302,24 @@ fn reflectance(cosine: f64, eta: f64) -> f64 {
     let r0 = ((1.0 - eta) / (1.0 + eta)).powi(2);
     r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
 }
+
+/// A surface which glows evenly from both sides and reflects nothing.
+pub struct DiffuseLight {
+    emit: Colour,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Colour) -> Self {
+        DiffuseLight { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn sample(&self, _col: &Collision, _wo: Vec3, _rng: &mut dyn RngCore) -> Option<BsdfSample> {
+        None
+    }
+
+    fn emitted(&self, _col: &Collision) -> Colour {
+        self.emit
+    }
+}
@@ -0,0 +1,228 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use rand::{Rng, RngCore};
+
+use crate::{
+    basis, mix, Aabb, BsdfSample, Collider, Collision, Colour, Material, Ray, SharedTexture,
+    Texture, Vec3,
+};
+
+/// A volume of fog or smoke of even density, filling the inside of a closed `boundary`.
+///
+/// Rays passing through are scattered at random distances with the medium's phase function,
+/// so that the fraction getting through falls exponentially with the distance they travel
+/// inside, as the Beer-Lambert law says. Collisions with the medium have no meaningful normal.
+pub struct ConstantMedium<C> {
+    pub boundary: C,
+    /// Chance of scattering per unit distance travelled.
+    pub density: f64,
+    /// How light scatters off particles of the medium, usually [`Isotropic`] or
+    /// [`HenyeyGreenstein`].
+    pub phase: Arc<dyn Material + Send + Sync>,
+}
+
+impl<C: Collider> ConstantMedium<C> {
+    pub fn new(boundary: C, density: f64, phase: impl Material + Send + Sync + 'static) -> Self {
+        ConstantMedium {
+            boundary,
+            density,
+            phase: Arc::new(phase),
+        }
+    }
+}
+
+impl<C: Collider> Collider for ConstantMedium<C> {
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
+        let entry = self
+            .boundary
+            .collide(ray, (f64::NEG_INFINITY, f64::INFINITY))?
+            .t;
+        let exit = self
+            .boundary
+            .collide(ray, (entry + BOUNDARY_EPSILON, f64::INFINITY))?
+            .t;
+        // Media with different boundaries or densities draw different random numbers from the
+        // same ray, so that where they overlap they scatter independently.
+        let salt = [self.density, entry, exit]
+            .iter()
+            .fold(MEDIUM_SALT, |h, v| mix(h ^ v.to_bits()));
+        let t = scatter_distance(
+            ray,
+            (entry.max(t_range.0), exit.min(t_range.1)),
+            self.density,
+            salt,
+        )?;
+        Some(particle(ray, t, self.phase.as_ref()))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+}
+
+/// Gap left after the boundary's first crossing when looking for the second, so as not to
+/// find the same one again.
+const BOUNDARY_EPSILON: f64 = 1e-4;
+
+/// Haze of even density filling all space within `extent` of wherever each ray starts.
+///
+/// Set as [`Raytracer::atmosphere`](crate::Raytracer::atmosphere) to fill the whole scene.
+/// Rays which travel `extent` without scattering or hitting anything see the background. With
+/// the default infinite extent, no ray ever does.
+pub struct Atmosphere {
+    /// Chance of scattering per unit distance travelled.
+    pub density: f64,
+    pub phase: Arc<dyn Material + Send + Sync>,
+    pub extent: f64,
+}
+
+impl Atmosphere {
+    pub fn new(density: f64, phase: impl Material + Send + Sync + 'static) -> Self {
+        Atmosphere {
+            density,
+            phase: Arc::new(phase),
+            extent: f64::INFINITY,
+        }
+    }
+}
+
+impl Collider for Atmosphere {
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
+        let far = t_range.1.min(self.extent / ray.dir.length());
+        let t = scatter_distance(ray, (t_range.0, far), self.density, ATMOSPHERE_SALT)?;
+        Some(particle(ray, t, self.phase.as_ref()))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
+
+const MEDIUM_SALT: u64 = 0x6d65_6469_756d;
+const ATMOSPHERE_SALT: u64 = 0x6174_6d6f_7370;
+
+/// Where along `ray`, between `near` and `far`, it scatters in a medium of the given density,
+/// if it does.
+///
+/// The random number needed comes from the ray's [random bits](Ray::random), mixed with `near`
+/// and `salt` so that each medium the ray passes through gets its own. `far` is left out: the
+/// search for the nearest collision only ever pulls it in, so the same ray always scatters at
+/// the same point measured from `near`, or not at all once `far` is closer than that.
+fn scatter_distance(ray: Ray, (near, far): (f64, f64), density: f64, salt: u64) -> Option<f64> {
+    if near >= far {
+        return None;
+    }
+    let hash = mix(mix(salt ^ ray.random) ^ near.to_bits());
+    // Uniform on (0, 1], so that the logarithm is finite.
+    let xi = ((hash >> 11) + 1) as f64 / (1u64 << 53) as f64;
+
+    let length = ray.dir.length();
+    let distance = -xi.ln() / density;
+    if distance > (far - near) * length {
+        return None;
+    }
+    Some(near + distance / length)
+}
+
+/// A collision with a particle of a medium, facing back along the ray.
+fn particle(ray: Ray, t: f64, phase: &dyn Material) -> Collision<'_> {
+    Collision::from_ray(ray, t, -ray.dir.unit(), phase)
+}
+
+/// A phase function which scatters light equally in all directions, tinted by `albedo`.
+pub struct Isotropic {
+    albedo: SharedTexture,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Colour) -> Self {
+        Isotropic::textured(albedo)
+    }
+
+    pub fn textured(albedo: impl Texture + Send + Sync + 'static) -> Self {
+        Isotropic {
+            albedo: Arc::new(albedo),
+        }
+    }
+}
+
+impl Material for Isotropic {
+    fn sample(&self, col: &Collision, _wo: Vec3, rng: &mut dyn RngCore) -> Option<BsdfSample> {
+        Some(BsdfSample {
+            dir: Vec3::random_unit(rng),
+            weight: self.albedo.value(col.uv, col.point),
+            pdf: (4.0 * PI).recip(),
+        })
+    }
+
+    fn eval(&self, col: &Collision, _wo: Vec3, _wi: Vec3) -> Colour {
+        self.albedo.value(col.uv, col.point) * (4.0 * PI).recip()
+    }
+
+    fn pdf(&self, _col: &Collision, _wo: Vec3, _wi: Vec3) -> f64 {
+        (4.0 * PI).recip()
+    }
+}
+
+/// The Henyey-Greenstein phase function, which favours scattering forwards when `g` is
+/// positive and backwards when it is negative. `g` is the mean cosine of the angle light turns
+/// through, between -1 and 1, and 0 gives [`Isotropic`] scattering.
+pub struct HenyeyGreenstein {
+    albedo: SharedTexture,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(albedo: Colour, g: f64) -> Self {
+        HenyeyGreenstein::textured(albedo, g)
+    }
+
+    pub fn textured(albedo: impl Texture + Send + Sync + 'static, g: f64) -> Self {
+        HenyeyGreenstein {
+            albedo: Arc::new(albedo),
+            // Fully forwards or backwards is a delta function, which can't be sampled by density.
+            g: g.clamp(-0.999, 0.999),
+        }
+    }
+
+    /// Density of light carrying on at an angle with cosine `cos` to its original direction.
+    fn phase(&self, cos: f64) -> f64 {
+        let g = self.g;
+        let denominator = 1.0 + g * g - 2.0 * g * cos;
+        (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn sample(&self, col: &Collision, wo: Vec3, rng: &mut dyn RngCore) -> Option<BsdfSample> {
+        let g = self.g;
+        let xi = rng.gen::<f64>();
+        let cos = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let s = (1.0 - g * g) / (1.0 + g - 2.0 * g * xi);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng.gen::<f64>();
+
+        // Light carrying straight on from `dir` leaves along `wo`, so `dir` is near -`wo` when
+        // scattering forwards.
+        let forward = -wo.unit();
+        let (u, v) = basis(forward);
+        let dir = u * (sin * phi.cos()) + v * (sin * phi.sin()) + forward * cos;
+        Some(BsdfSample {
+            dir,
+            weight: self.albedo.value(col.uv, col.point),
+            pdf: self.phase(cos),
+        })
+    }
+
+    fn eval(&self, col: &Collision, wo: Vec3, wi: Vec3) -> Colour {
+        self.albedo.value(col.uv, col.point) * self.pdf(col, wo, wi)
+    }
+
+    fn pdf(&self, _col: &Collision, wo: Vec3, wi: Vec3) -> f64 {
+        self.phase(-wo.unit().dot(wi.unit()))
+    }
+}
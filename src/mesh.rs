@@ -0,0 +1,373 @@
+use std::{error::Error, fmt, sync::Arc};
+
+use rand::{Rng, RngCore};
+
+use crate::{
+    bvh::BvhTree,
+    light::{sample_triangle, to_solid_angle},
+    Aabb, Collider, Collision, Light, LightSample, Material, Ray, Vec3,
+};
+
+/// Möller–Trumbore intersection, giving the distance along the ray and the barycentric weights
+/// of the second and third vertices.
+fn intersect(ray: Ray, [a, b, c]: [Vec3; 3], t_range: (f64, f64)) -> Option<(f64, f64, f64)> {
+    let ab = b - a;
+    let ac = c - a;
+    let p = ray.dir.cross(ac);
+    let det = ab.dot(p);
+    if det.abs() < 1e-12 {
+        return None;
+    }
+    let inv_det = det.recip();
+
+    let s = ray.orig - a;
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(ab);
+    let v = ray.dir.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = ac.dot(q) * inv_det;
+    if t < t_range.0 || t > t_range.1 {
+        return None;
+    }
+
+    Some((t, u, v))
+}
+
+fn collision<'a>(
+    ray: Ray,
+    vertices: [Vec3; 3],
+    normals: Option<[Vec3; 3]>,
+    texcoords: Option<[(f64, f64); 3]>,
+    t_range: (f64, f64),
+    material: &'a dyn Material,
+) -> Option<Collision<'a>> {
+    let (t, u, v) = intersect(ray, vertices, t_range)?;
+    let [a, b, c] = vertices;
+    let weights = [1.0 - u - v, u, v];
+
+    let geometric = (b - a).cross(c - a).unit();
+    let mut col = Collision::from_ray(ray, t, geometric, material);
+    if let Some([na, nb, nc]) = normals {
+        let shading = na * weights[0] + nb * weights[1] + nc * weights[2];
+        col = col.with_shading_normal(shading.unit());
+    }
+    col.barycentric = Some(weights);
+    col.uv = match texcoords {
+        Some([ta, tb, tc]) => (
+            ta.0 * weights[0] + tb.0 * weights[1] + tc.0 * weights[2],
+            ta.1 * weights[0] + tb.1 * weights[1] + tc.1 * weights[2],
+        ),
+        None => (u, v),
+    };
+    Some(col)
+}
+
+fn bounds([a, b, c]: [Vec3; 3]) -> Aabb {
+    Aabb::new(a, b).grow(c)
+}
+
+/// The geometric normal of a triangle, with a length of twice its area.
+fn area_normal([a, b, c]: [Vec3; 3]) -> Vec3 {
+    (b - a).cross(c - a)
+}
+
+#[derive(Clone, Debug)]
+pub struct Triangle<M>
+where
+    M: Material,
+{
+    pub vertices: [Vec3; 3],
+    pub normals: Option<[Vec3; 3]>,
+    pub material: Arc<M>,
+}
+
+impl<M: Material> Triangle<M> {
+    /// A flat-shaded triangle. Vertices wound anticlockwise when seen from the front.
+    pub fn new(vertices: [Vec3; 3], material: Arc<M>) -> Self {
+        Triangle {
+            vertices,
+            normals: None,
+            material,
+        }
+    }
+
+    /// A triangle whose shading normal is interpolated between the given vertex normals.
+    pub fn with_normals(vertices: [Vec3; 3], normals: [Vec3; 3], material: Arc<M>) -> Self {
+        Triangle {
+            vertices,
+            normals: Some(normals),
+            material,
+        }
+    }
+}
+
+impl<M: Material> Collider for Triangle<M> {
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
+        collision(
+            ray,
+            self.vertices,
+            self.normals,
+            None,
+            t_range,
+            self.material.as_ref(),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(bounds(self.vertices))
+    }
+}
+
+impl<M: Material> Light for Triangle<M> {
+    /// Chooses uniformly from the triangle's area.
+    fn sample(&self, origin: Vec3, rng: &mut dyn RngCore) -> Option<LightSample> {
+        let normal = area_normal(self.vertices);
+        let point = sample_triangle(self.vertices, rng);
+        to_solid_angle(2.0 / normal.length(), origin, point, normal)
+    }
+
+    fn pdf(&self, ray: Ray, t_range: (f64, f64)) -> f64 {
+        let normal = area_normal(self.vertices);
+        self.collide(ray, t_range)
+            .and_then(|c| to_solid_angle(2.0 / normal.length(), ray.orig, c.point, normal))
+            .map_or(0.0, |s| s.pdf)
+    }
+}
+
+#[derive(Debug)]
+pub enum MeshError {
+    /// A triangle refers to a vertex past the end of the positions.
+    MissingVertex { index: usize, vertices: usize },
+    /// There isn't exactly one normal or texture coordinate for each vertex.
+    AttributeCount {
+        attribute: &'static str,
+        vertices: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshError::MissingVertex { index, vertices } => write!(
+                f,
+                "mesh triangle refers to vertex {} but there are only {}",
+                index, vertices
+            ),
+            MeshError::AttributeCount {
+                attribute,
+                vertices,
+                found,
+            } => write!(
+                f,
+                "mesh has {} vertices but {} {}, it needs one for each",
+                vertices, found, attribute
+            ),
+        }
+    }
+}
+
+impl Error for MeshError {}
+
+/// A set of triangles sharing one vertex buffer and one material, indexed by a BVH of its own
+/// so that the whole mesh can sit in a scene as a single collider.
+#[derive(Clone, Debug)]
+pub struct TriangleMesh<M>
+where
+    M: Material,
+{
+    positions: Vec<Vec3>,
+    normals: Option<Vec<Vec3>>,
+    texcoords: Option<Vec<(f64, f64)>>,
+    triangles: Vec<[usize; 3]>,
+    material: Arc<M>,
+    tree: BvhTree,
+    /// Running total of the triangles' areas, for choosing points uniformly over the mesh.
+    cumulative_area: Vec<f64>,
+}
+
+impl<M: Material> TriangleMesh<M> {
+    /// A flat-shaded mesh. Each triangle is three indices into `positions`.
+    pub fn new(
+        positions: Vec<Vec3>,
+        triangles: Vec<[usize; 3]>,
+        material: Arc<M>,
+    ) -> Result<Self, MeshError> {
+        Self::build(positions, None, triangles, material)
+    }
+
+    /// A smooth-shaded mesh, with one normal in `normals` for each vertex in `positions`.
+    pub fn with_normals(
+        positions: Vec<Vec3>,
+        normals: Vec<Vec3>,
+        triangles: Vec<[usize; 3]>,
+        material: Arc<M>,
+    ) -> Result<Self, MeshError> {
+        check_count("normals", positions.len(), normals.len())?;
+        Self::build(positions, Some(normals), triangles, material)
+    }
+
+    fn build(
+        positions: Vec<Vec3>,
+        normals: Option<Vec<Vec3>>,
+        triangles: Vec<[usize; 3]>,
+        material: Arc<M>,
+    ) -> Result<Self, MeshError> {
+        if let Some(&index) = triangles.iter().flatten().find(|&&i| i >= positions.len()) {
+            return Err(MeshError::MissingVertex {
+                index,
+                vertices: positions.len(),
+            });
+        }
+
+        let mut mesh = TriangleMesh {
+            positions,
+            normals,
+            texcoords: None,
+            triangles,
+            material,
+            tree: BvhTree::default(),
+            cumulative_area: Vec::new(),
+        };
+        let boxes: Vec<Aabb> = (0..mesh.len()).map(|i| bounds(mesh.vertices(i))).collect();
+        mesh.tree = BvhTree::new(&boxes);
+        mesh.cumulative_area = (0..mesh.len())
+            .scan(0.0, |total, i| {
+                *total += area_normal(mesh.vertices(i)).length() / 2.0;
+                Some(*total)
+            })
+            .collect();
+        Ok(mesh)
+    }
+
+    /// Attach texture coordinates, one for each vertex in the mesh.
+    pub fn textured(mut self, texcoords: Vec<(f64, f64)>) -> Result<Self, MeshError> {
+        check_count("texture coordinates", self.positions.len(), texcoords.len())?;
+        self.texcoords = Some(texcoords);
+        Ok(self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+
+    pub fn positions(&self) -> &[Vec3] {
+        &self.positions
+    }
+
+    pub fn normals(&self) -> Option<&[Vec3]> {
+        self.normals.as_deref()
+    }
+
+    pub fn texcoords(&self) -> Option<&[(f64, f64)]> {
+        self.texcoords.as_deref()
+    }
+
+    pub fn triangles(&self) -> &[[usize; 3]] {
+        &self.triangles
+    }
+
+    fn vertices(&self, triangle: usize) -> [Vec3; 3] {
+        let [a, b, c] = self.triangles[triangle];
+        [self.positions[a], self.positions[b], self.positions[c]]
+    }
+
+    fn vertex_normals(&self, triangle: usize) -> Option<[Vec3; 3]> {
+        let normals = self.normals.as_ref()?;
+        let [a, b, c] = self.triangles[triangle];
+        Some([normals[a], normals[b], normals[c]])
+    }
+
+    fn vertex_texcoords(&self, triangle: usize) -> Option<[(f64, f64); 3]> {
+        let texcoords = self.texcoords.as_ref()?;
+        let [a, b, c] = self.triangles[triangle];
+        Some([texcoords[a], texcoords[b], texcoords[c]])
+    }
+
+    fn area(&self) -> f64 {
+        self.cumulative_area.last().copied().unwrap_or(0.0)
+    }
+
+    /// The closest collision with the mesh, and the index of the triangle it was with.
+    fn closest(&self, ray: Ray, t_range: (f64, f64)) -> Option<(usize, Collision<'_>)> {
+        // Each hit the traversal accepts is closer than the last, so the last one wins.
+        let mut triangle = None;
+        let col = self.tree.traverse(ray, t_range, |i, range| {
+            let col = collision(
+                ray,
+                self.vertices(i),
+                self.vertex_normals(i),
+                self.vertex_texcoords(i),
+                range,
+                self.material.as_ref(),
+            )?;
+            triangle = Some(i);
+            Some(col)
+        })?;
+        Some((triangle?, col))
+    }
+}
+
+fn check_count(attribute: &'static str, vertices: usize, found: usize) -> Result<(), MeshError> {
+    if vertices != found {
+        return Err(MeshError::AttributeCount {
+            attribute,
+            vertices,
+            found,
+        });
+    }
+    Ok(())
+}
+
+impl<M: Material> Collider for TriangleMesh<M> {
+    fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
+        self.closest(ray, t_range).map(|(_, col)| col)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.tree.bounds()
+    }
+}
+
+impl<M: Material> Light for TriangleMesh<M> {
+    /// Chooses uniformly from the whole surface of the mesh.
+    fn sample(&self, origin: Vec3, rng: &mut dyn RngCore) -> Option<LightSample> {
+        let area = self.area();
+        if area <= 0.0 {
+            return None;
+        }
+        let target = rng.gen::<f64>() * area;
+        let i = self
+            .cumulative_area
+            .partition_point(|&a| a <= target)
+            .min(self.len() - 1);
+
+        let vertices = self.vertices(i);
+        let point = sample_triangle(vertices, rng);
+        to_solid_angle(area.recip(), origin, point, area_normal(vertices))
+    }
+
+    fn pdf(&self, ray: Ray, t_range: (f64, f64)) -> f64 {
+        self.closest(ray, t_range)
+            .and_then(|(i, c)| {
+                to_solid_angle(
+                    self.area().recip(),
+                    ray.orig,
+                    c.point,
+                    area_normal(self.vertices(i)),
+                )
+            })
+            .map_or(0.0, |s| s.pdf)
+    }
+}
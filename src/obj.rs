@@ -0,0 +1,506 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    str::SplitWhitespace,
+    sync::Arc,
+};
+
+use crate::{
+    Collider, Colour, Dielectric, DiffuseLight, Lambertian, Material, MeshError, Metal, Scene,
+    TriangleMesh, Vec3,
+};
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io {
+        file: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        file: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// The file describes a mesh which can't be built.
+    Mesh {
+        file: PathBuf,
+        source: MeshError,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { file, source } => write!(f, "{}: {}", file.display(), source),
+            ObjError::Parse {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", file.display(), line, message),
+            ObjError::Mesh { file, source } => write!(f, "{}: {}", file.display(), source),
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io { source, .. } => Some(source),
+            ObjError::Mesh { source, .. } => Some(source),
+            ObjError::Parse { .. } => None,
+        }
+    }
+}
+
+/// Something wrong with a file which could be worked around, so it was loaded anyway.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Warning {
+    pub file: PathBuf,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file.display(), self.line, self.message)
+    }
+}
+
+/// What [`load_obj`] loaded.
+pub struct Obj {
+    pub scene: Scene,
+    /// Problems which were worked around.
+    pub warnings: Vec<Warning>,
+    /// Every file that was read: the OBJ file, then the MTL libraries it uses.
+    pub files: Vec<PathBuf>,
+}
+
+/// Load a Wavefront OBJ file, along with any MTL libraries it references, as one triangle mesh
+/// for each combination of group and material it uses.
+///
+/// Polygons are fan-triangulated, so they are expected to be convex. Faces with no material,
+/// or one missing from the MTL libraries, are given a grey [`Lambertian`], with a [`Warning`]
+/// for each missing one. MTL materials are mapped onto the closest built-in material:
+///
+/// * materials which glow (`Ke` above black) become a [`DiffuseLight`] giving off `Ke`;
+/// * transparent materials (`d` below 1, or `illum` 4, 6, 7 or 9) become a [`Dielectric`] with
+///   refractive index `Ni`;
+/// * reflective materials (`illum` 3, 5 or 8) become a [`Metal`] coloured by `Ks`, with fuzz
+///   derived from the specular exponent `Ns`;
+/// * everything else becomes a [`Lambertian`] coloured by `Kd`.
+pub fn load_obj(path: impl AsRef<Path>) -> Result<Obj, ObjError> {
+    let path = path.as_ref();
+    let reader = open(path)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut files = vec![path.to_owned()];
+    let (scene, warnings) = parse_obj(reader, path, |lib| {
+        let lib = dir.join(lib);
+        let materials = parse_mtl(open(&lib)?, &lib)?;
+        files.push(lib);
+        Ok(materials)
+    })?;
+    Ok(Obj {
+        scene,
+        warnings,
+        files,
+    })
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, ObjError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|source| ObjError::Io {
+            file: path.to_owned(),
+            source,
+        })
+}
+
+/// Iterate over the statements in an OBJ or MTL file, skipping blanks and comments and
+/// yielding each remaining line with its number.
+fn statements<'a, R: BufRead + 'a>(
+    reader: R,
+    file: &'a Path,
+) -> impl Iterator<Item = Result<(usize, String), ObjError>> + 'a {
+    let mut lines = reader.lines().enumerate();
+    std::iter::from_fn(move || loop {
+        let (i, line) = lines.next()?;
+        let line = match line {
+            Ok(line) => line,
+            Err(source) => {
+                return Some(Err(ObjError::Io {
+                    file: file.to_owned(),
+                    source,
+                }))
+            }
+        };
+        let line = line.split('#').next().unwrap().trim();
+        if !line.is_empty() {
+            return Some(Ok((i + 1, line.to_owned())));
+        }
+    })
+}
+
+struct Line<'a> {
+    file: &'a Path,
+    number: usize,
+}
+
+impl Line<'_> {
+    fn error(&self, message: impl Into<String>) -> ObjError {
+        ObjError::Parse {
+            file: self.file.to_owned(),
+            line: self.number,
+            message: message.into(),
+        }
+    }
+
+    /// Parse the remaining arguments as numbers, of which there must be between `min` and
+    /// `max` inclusive.
+    fn numbers(&self, args: SplitWhitespace, min: usize, max: usize) -> Result<Vec<f64>, ObjError> {
+        let numbers = args
+            .map(|s| {
+                s.parse()
+                    .map_err(|_| self.error(format!("invalid number `{}`", s)))
+            })
+            .collect::<Result<Vec<f64>, _>>()?;
+        if numbers.len() < min || numbers.len() > max {
+            let expected = if min == max {
+                min.to_string()
+            } else {
+                format!("{} to {}", min, max)
+            };
+            return Err(self.error(format!(
+                "expected {} numbers, found {}",
+                expected,
+                numbers.len()
+            )));
+        }
+        Ok(numbers)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Mtl {
+    diffuse: Colour,
+    specular: Colour,
+    emission: Colour,
+    exponent: f64,
+    eta: f64,
+    dissolve: f64,
+    illum: u32,
+}
+
+impl Default for Mtl {
+    fn default() -> Self {
+        Mtl {
+            diffuse: Colour::new(0.8, 0.8, 0.8),
+            specular: Colour::BLACK,
+            emission: Colour::BLACK,
+            exponent: 0.0,
+            eta: 1.5,
+            dissolve: 1.0,
+            illum: 1,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum ObjMaterial {
+    Lambertian(Arc<Lambertian>),
+    Metal(Arc<Metal>),
+    Dielectric(Arc<Dielectric>),
+    Light(Arc<DiffuseLight>),
+}
+
+impl From<&Mtl> for ObjMaterial {
+    fn from(mtl: &Mtl) -> Self {
+        if mtl.emission != Colour::BLACK {
+            ObjMaterial::Light(Arc::new(DiffuseLight::new(mtl.emission)))
+        } else if mtl.dissolve < 1.0 || matches!(mtl.illum, 4 | 6 | 7 | 9) {
+            ObjMaterial::Dielectric(Arc::new(Dielectric::new(mtl.eta)))
+        } else if matches!(mtl.illum, 3 | 5 | 8) {
+            // Treat the Phong exponent as a Beckmann-style roughness.
+            let fuzz = (2.0 / (mtl.exponent.max(0.0) + 2.0)).sqrt();
+            ObjMaterial::Metal(Arc::new(Metal::new(mtl.specular, fuzz)))
+        } else {
+            ObjMaterial::Lambertian(Arc::new(Lambertian::new(mtl.diffuse)))
+        }
+    }
+}
+
+impl ObjMaterial {
+    fn mesh(&self, mesh: MeshBuilder) -> Result<Box<dyn Collider + Send + Sync>, MeshError> {
+        fn build<M: Material + Send + Sync + 'static>(
+            mesh: MeshBuilder,
+            material: &Arc<M>,
+        ) -> Result<Box<dyn Collider + Send + Sync>, MeshError> {
+            let mut built = match mesh.normals {
+                Some(normals) => TriangleMesh::with_normals(
+                    mesh.positions,
+                    normals,
+                    mesh.triangles,
+                    material.clone(),
+                )?,
+                None => TriangleMesh::new(mesh.positions, mesh.triangles, material.clone())?,
+            };
+            if let Some(texcoords) = mesh.texcoords {
+                built = built.textured(texcoords)?;
+            }
+            Ok(Box::new(built))
+        }
+
+        match self {
+            ObjMaterial::Lambertian(m) => build(mesh, m),
+            ObjMaterial::Metal(m) => build(mesh, m),
+            ObjMaterial::Dielectric(m) => build(mesh, m),
+            ObjMaterial::Light(m) => build(mesh, m),
+        }
+    }
+}
+
+fn parse_mtl<R: BufRead>(reader: R, file: &Path) -> Result<Vec<(String, Mtl)>, ObjError> {
+    let mut materials: Vec<(String, Mtl)> = Vec::new();
+
+    for statement in statements(reader, file) {
+        let (number, text) = statement?;
+        let line = Line { file, number };
+        let mut args = text.split_whitespace();
+        let keyword = args.next().unwrap();
+
+        if keyword == "newmtl" {
+            let name = args
+                .next()
+                .ok_or_else(|| line.error("material has no name"))?;
+            materials.push((name.to_owned(), Mtl::default()));
+            continue;
+        }
+
+        let mtl = match materials.last_mut() {
+            Some((_, mtl)) => mtl,
+            None if ["Kd", "Ks", "Ke", "Ns", "Ni", "d", "Tr", "illum"].contains(&keyword) => {
+                return Err(line.error(format!("`{}` before any `newmtl`", keyword)))
+            }
+            None => continue,
+        };
+        match keyword {
+            "Kd" | "Ks" | "Ke" => {
+                let rgb = line.numbers(args, 1, 3)?;
+                // A single value means grey.
+                let colour = match rgb[..] {
+                    [v] => Colour::new(v, v, v),
+                    [r, g, b] => Colour::new(r, g, b),
+                    _ => return Err(line.error("expected 1 or 3 numbers, found 2")),
+                };
+                match keyword {
+                    "Kd" => mtl.diffuse = colour,
+                    "Ks" => mtl.specular = colour,
+                    _ => mtl.emission = colour,
+                }
+            }
+            "Ns" => mtl.exponent = line.numbers(args, 1, 1)?[0],
+            "Ni" => mtl.eta = line.numbers(args, 1, 1)?[0],
+            "d" => mtl.dissolve = line.numbers(args, 1, 1)?[0],
+            "Tr" => mtl.dissolve = 1.0 - line.numbers(args, 1, 1)?[0],
+            "illum" => {
+                let s = args.next().unwrap_or_default();
+                mtl.illum = s
+                    .parse()
+                    .map_err(|_| line.error(format!("invalid illumination model `{}`", s)))?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+#[derive(Default)]
+struct MeshBuilder {
+    positions: Vec<Vec3>,
+    normals: Option<Vec<Vec3>>,
+    texcoords: Option<Vec<(f64, f64)>>,
+    triangles: Vec<[usize; 3]>,
+    // Each distinct (position, texcoord, normal) triple in the file becomes one mesh vertex.
+    vertices: HashMap<(usize, Option<usize>, Option<usize>), usize>,
+}
+
+fn parse_obj<R, F>(
+    reader: R,
+    file: &Path,
+    mut load_mtl: F,
+) -> Result<(Scene, Vec<Warning>), ObjError>
+where
+    R: BufRead,
+    F: FnMut(&str) -> Result<Vec<(String, Mtl)>, ObjError>,
+{
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut texcoords: Vec<(f64, f64)> = Vec::new();
+
+    let mut library: HashMap<String, ObjMaterial> = HashMap::new();
+    let default = ObjMaterial::Lambertian(Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5))));
+
+    let mut warnings = Vec::new();
+
+    let mut group = String::new();
+    let mut material: Option<String> = None;
+    // Meshes in the order they were first used, so output is stable from run to run.
+    let mut meshes: Vec<((String, Option<String>), MeshBuilder)> = Vec::new();
+
+    for statement in statements(reader, file) {
+        let (number, text) = statement?;
+        let line = Line { file, number };
+        let mut args = text.split_whitespace();
+
+        match args.next().unwrap() {
+            "v" => {
+                // An optional fourth coordinate is a weight, which we ignore.
+                let v = line.numbers(args, 3, 4)?;
+                positions.push(Vec3::new(v[0], v[1], v[2]));
+            }
+            "vn" => {
+                let n = line.numbers(args, 3, 3)?;
+                normals.push(Vec3::new(n[0], n[1], n[2]).unit());
+            }
+            "vt" => {
+                let t = line.numbers(args, 1, 3)?;
+                texcoords.push((t[0], t.get(1).copied().unwrap_or(0.0)));
+            }
+            "g" | "o" => group = args.collect::<Vec<_>>().join(" "),
+            "mtllib" => {
+                for lib in args {
+                    library.extend(
+                        load_mtl(lib)?
+                            .iter()
+                            .map(|(name, mtl)| (name.clone(), ObjMaterial::from(mtl))),
+                    );
+                }
+            }
+            "usemtl" => {
+                let name = args
+                    .next()
+                    .ok_or_else(|| line.error("`usemtl` needs a name"))?;
+                material = if library.contains_key(name) {
+                    Some(name.to_owned())
+                } else {
+                    warnings.push(Warning {
+                        file: file.to_owned(),
+                        line: number,
+                        message: format!("unknown material `{}`, using the default", name),
+                    });
+                    None
+                };
+            }
+            "f" => {
+                let corners = args
+                    .map(|corner| {
+                        parse_corner(
+                            &line,
+                            corner,
+                            (positions.len(), texcoords.len(), normals.len()),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if corners.len() < 3 {
+                    return Err(line.error(format!(
+                        "face needs at least 3 vertices, found {}",
+                        corners.len()
+                    )));
+                }
+
+                let key = (group.clone(), material.clone());
+                let mesh = match meshes.iter().position(|(k, _)| *k == key) {
+                    Some(i) => &mut meshes[i].1,
+                    None => {
+                        meshes.push((key, MeshBuilder::default()));
+                        &mut meshes.last_mut().unwrap().1
+                    }
+                };
+
+                let mut indices = Vec::with_capacity(corners.len());
+                for corner @ (v, vt, vn) in corners {
+                    let next = mesh.positions.len();
+                    let index = *mesh.vertices.entry(corner).or_insert(next);
+                    if index == next {
+                        mesh.positions.push(positions[v]);
+                        push_attribute(&mut mesh.texcoords, vt.map(|vt| texcoords[vt]), index);
+                        push_attribute(&mut mesh.normals, vn.map(|vn| normals[vn]), index);
+                    }
+                    indices.push(index);
+                }
+
+                for i in 1..indices.len() - 1 {
+                    mesh.triangles
+                        .push([indices[0], indices[i], indices[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let scene = meshes
+        .into_iter()
+        .filter(|(_, mesh)| !mesh.triangles.is_empty())
+        .map(|((_, material), mesh)| {
+            material
+                .map_or(&default, |name| &library[&name])
+                .mesh(mesh)
+                .map_err(|source| ObjError::Mesh {
+                    file: file.to_owned(),
+                    source,
+                })
+        })
+        .collect::<Result<_, _>>()?;
+    Ok((scene, warnings))
+}
+
+/// Record an optional per-vertex attribute. The mesh only keeps an attribute if every vertex
+/// has one, so the first vertex without it discards the rest.
+fn push_attribute<T>(attribute: &mut Option<Vec<T>>, value: Option<T>, index: usize) {
+    match (attribute.as_mut(), value) {
+        (Some(values), Some(value)) => values.push(value),
+        (None, Some(value)) if index == 0 => *attribute = Some(vec![value]),
+        _ => *attribute = None,
+    }
+}
+
+/// Parse one `v`, `v/vt`, `v//vn` or `v/vt/vn` face corner into zero-based indices, resolving
+/// negative indices relative to the current end of each list.
+fn parse_corner(
+    line: &Line,
+    corner: &str,
+    (positions, texcoords, normals): (usize, usize, usize),
+) -> Result<(usize, Option<usize>, Option<usize>), ObjError> {
+    let index = |s: &str, len: usize, what: &str| -> Result<usize, ObjError> {
+        let i: i64 = s
+            .parse()
+            .map_err(|_| line.error(format!("invalid {} index `{}`", what, s)))?;
+        let resolved = if i < 0 { len as i64 + i } else { i - 1 };
+        if i == 0 || resolved < 0 || resolved >= len as i64 {
+            return Err(line.error(format!(
+                "{} index {} out of range, there are {}",
+                what, i, len
+            )));
+        }
+        Ok(resolved as usize)
+    };
+
+    let mut parts = corner.split('/');
+    let v = index(parts.next().unwrap(), positions, "vertex")?;
+    let vt = match parts.next() {
+        None | Some("") => None,
+        Some(s) => Some(index(s, texcoords, "texture coordinate")?),
+    };
+    let vn = match parts.next() {
+        None | Some("") => None,
+        Some(s) => Some(index(s, normals, "normal")?),
+    };
+    if parts.next().is_some() {
+        return Err(line.error(format!("invalid face vertex `{}`", corner)));
+    }
+    Ok((v, vt, vn))
+}
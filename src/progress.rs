@@ -0,0 +1,44 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Told how rendering is going, so that it can be shown. Called from every rendering thread,
+/// so it needs to be quick.
+pub trait Progress {
+    /// Rendering is starting, with `pixels` pixels to sample, counting each once for each pass
+    /// it is in.
+    fn started(&self, _pixels: u64) {}
+
+    /// Another `pixels` pixels have been sampled, taking `samples` samples between them.
+    fn advanced(&self, _pixels: u64, _samples: u64) {}
+
+    /// Rendering has stopped, whether it finished or was cancelled.
+    fn finished(&self) {}
+}
+
+/// Progress which isn't shown anywhere.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Silent;
+
+impl Progress for Silent {}
+
+/// A handle for stopping a render from another thread. Clones share the same flag, so keep
+/// one while the raytracer has another.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Stop the render as soon as the pixels being worked on are done.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
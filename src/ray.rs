@@ -4,11 +4,32 @@ use crate::Vec3;
 pub struct Ray {
     pub orig: Vec3,
     pub dir: Vec3,
+    /// The moment the ray is sent, for scenes with moving objects. Rays scattered from it
+    /// carry the same time.
+    pub time: f64,
+    /// Random bits for colliders which make random choices, such as where in a medium the ray
+    /// scatters. Whatever sends the ray draws them afresh from its random number generator.
+    pub random: u64,
 }
 
 impl Ray {
     pub fn new(orig: Vec3, dir: Vec3) -> Ray {
-        Ray { orig, dir }
+        Ray {
+            orig,
+            dir,
+            time: 0.0,
+            random: 0,
+        }
+    }
+
+    pub fn with_time(mut self, time: f64) -> Ray {
+        self.time = time;
+        self
+    }
+
+    pub fn with_random(mut self, random: u64) -> Ray {
+        self.random = random;
+        self
     }
 
     pub fn at(&self, t: f64) -> Vec3 {
@@ -1,33 +1,68 @@
-use std::iter::Sum;
+use std::ops::ControlFlow;
 use std::sync::Arc;
 
-use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
-use itertools::iproduct;
-use rand::{random, seq::SliceRandom};
-use rayon::{
-    iter::{IntoParallelRefIterator, ParallelIterator},
-    slice::ParallelSliceMut,
-};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+use rayon::iter::{ParallelBridge, ParallelIterator};
 
-use crate::{Blend, Camera, Collider, Colour, Ray, Scene};
+use crate::{
+    heatmap, Atmosphere, Background, Camera, CancelToken, Collider, Collision, Colour,
+    DisplayTransform, Gradient, Integrator, Lights, PathTracer, Progress, Ray, Region, Silent,
+    TileOrder,
+};
 
 pub struct Raytracer {
-    pub scene: Arc<Scene>,
+    pub scene: Arc<dyn Collider + Send + Sync>,
+    /// Lights in the scene to aim shadow rays at from non-specular surfaces. Each must also be
+    /// part of `scene`. With none, lights are only found by rays scattering into them.
+    pub lights: Lights,
     pub camera: Camera,
+    /// How to work out what each camera ray sees. Defaults to [`PathTracer`].
+    pub integrator: Arc<dyn Integrator + Send + Sync>,
 
     pub width: u32,
     pub height: u32,
+    /// The part of the image to render, if not all of it. Pixels come out the same as they
+    /// would in the whole image, so a region can be redone with more samples and pasted back.
+    pub region: Option<Region>,
+    /// Width and height of the squares of pixels handed to each thread at a time.
+    pub tile_size: u32,
+    pub tile_order: TileOrder,
 
+    /// The most samples any pixel gets.
     pub samples_per_pixel: u32,
+    /// Stop sampling each pixel once it is clean enough, rather than always taking
+    /// `samples_per_pixel`.
+    pub adaptive: Option<Adaptive>,
     pub bounce_depth: u32,
+    /// Render tiles in a random order instead of `tile_order`.
     pub shuffle: bool,
 
-    pub gamma: f64,
+    /// Seed for every random choice made while rendering. The same seed always gives the same
+    /// image, however many threads render it.
+    pub seed: u64,
+    /// Stands for everything the other settings here don't, in the raytracer's
+    /// [`fingerprint`](Raytracer::fingerprint): the scene, its lights and background, the
+    /// camera and the integrator. [`load_scene`](crate::load_scene) makes one from the scene
+    /// file and every file it refers to, and [`scene_id`](crate::scene_id) from any other
+    /// description. Checkpoints and distributed renders need one.
+    pub scene_id: Option<u64>,
+
+    /// How the image is made ready to show, from its linear radiance.
+    pub display: DisplayTransform,
+    pub background: Arc<dyn Background + Send + Sync>,
+    /// Haze filling the whole scene, if any.
+    pub atmosphere: Option<Atmosphere>,
+    /// Told how rendering is going. Defaults to [`Silent`].
+    pub progress: Arc<dyn Progress + Send + Sync>,
+    /// Checked while rendering, which stops early with whatever has been rendered so far if
+    /// it is cancelled.
+    pub cancel: CancelToken,
 }
 
 impl Raytracer {
     pub fn new(
-        scene: Arc<Scene>,
+        scene: Arc<dyn Collider + Send + Sync>,
         camera: Camera,
         width: u32,
         height: u32,
@@ -36,117 +71,365 @@ impl Raytracer {
     ) -> Self {
         Raytracer {
             scene,
+            lights: Vec::new(),
             camera,
+            integrator: Arc::new(PathTracer::default()),
             width,
             height,
+            region: None,
+            tile_size: 16,
+            tile_order: TileOrder::Scanline,
             samples_per_pixel,
+            adaptive: None,
             bounce_depth,
             shuffle: false,
-            gamma: 2.0,
+            seed: 0,
+            scene_id: None,
+            display: DisplayTransform::default(),
+            background: Arc::new(Gradient::sky()),
+            atmosphere: None,
+            progress: Arc::new(Silent),
+            cancel: CancelToken::new(),
         }
     }
 }
 
 impl Raytracer {
+    /// The first thing `ray` meets within `t_range`: either a surface in the scene, or a
+    /// particle of the atmosphere in front of it.
+    pub fn collide(&self, ray: Ray, t_range: (f64, f64)) -> Option<Collision<'_>> {
+        let hit = self.scene.collide(ray, t_range);
+        match &self.atmosphere {
+            Some(atmosphere) => {
+                let far = hit.as_ref().map_or(t_range.1, |h| h.t);
+                atmosphere.collide(ray, (t_range.0, far)).or(hit)
+            }
+            None => hit,
+        }
+    }
+
+    /// The part of the image being rendered: the `region`, or else all of it.
+    pub fn frame(&self) -> Region {
+        let whole = Region::new(0, 0, self.width, self.height);
+        match self.region {
+            Some(region) => {
+                assert!(
+                    region.fits(self.width, self.height),
+                    "region {:?} isn't inside the {}x{} image",
+                    region,
+                    self.width,
+                    self.height
+                );
+                region
+            }
+            None => whole,
+        }
+    }
+
+    /// Render the image, passed through the display transform.
     pub fn render(&self) -> Vec<Colour> {
-        let coords = {
-            let mut coords: Vec<(u32, u32)> =
-                iproduct!((0..self.height).rev(), 0..self.width).collect();
-
-            if self.shuffle {
-                // Shuffle the coordinates. Will make ~0 difference to overall performance
-                // but will make our progress bar move more evenly!
-                coords.shuffle(&mut rand::thread_rng());
+        self.render_progressive(self.samples_per_pixel, |_| ControlFlow::Continue(()))
+            .resolve(&self.display)
+    }
+
+    /// Render the image as linear radiance, with no tone mapping or clamping, for encoding
+    /// in a high dynamic range format.
+    pub fn render_linear(&self) -> Vec<Colour> {
+        self.render_progressive(self.samples_per_pixel, |_| ControlFlow::Continue(()))
+            .linear()
+    }
+
+    /// Render the image in passes of up to `pass_samples` samples per pixel, up to
+    /// `samples_per_pixel` in all, calling `on_pass` with the image so far after each one.
+    /// Rendering stops early if `on_pass` breaks, or if cancelled part way through a pass,
+    /// when some pixels will have more samples than others and some may have none.
+    ///
+    /// Every sample is drawn the same way however the passes are split up, so the finished
+    /// image is the same as one from [`render`](Raytracer::render).
+    pub fn render_progressive(
+        &self,
+        pass_samples: u32,
+        on_pass: impl FnMut(&Accumulation) -> ControlFlow<()>,
+    ) -> Accumulation {
+        let frame = self.frame();
+        let image = Accumulation::new(frame.width, frame.height);
+        self.render_from(image, pass_samples, on_pass)
+    }
+
+    /// Carry on rendering `image`, which must be the size of this raytracer's frame, as
+    /// [`render_progressive`](Raytracer::render_progressive) would have done had it not
+    /// stopped. Used to resume from a checkpoint.
+    pub fn render_from(
+        &self,
+        mut image: Accumulation,
+        pass_samples: u32,
+        mut on_pass: impl FnMut(&Accumulation) -> ControlFlow<()>,
+    ) -> Accumulation {
+        let frame = self.frame();
+        assert!(
+            image.width == frame.width && image.height == frame.height,
+            "image is {}x{}, not {}x{}",
+            image.width,
+            image.height,
+            frame.width,
+            frame.height
+        );
+        let pass_samples = pass_samples.max(1);
+        let passes = self
+            .samples_per_pixel
+            .saturating_sub(image.samples)
+            .div_ceil(pass_samples);
+        self.progress
+            .started(passes as u64 * frame.height as u64 * frame.width as u64);
+
+        while image.samples < self.samples_per_pixel && !self.cancel.is_cancelled() {
+            let end = (image.samples + pass_samples).min(self.samples_per_pixel);
+            self.sample(&mut image, end);
+            if self.cancel.is_cancelled() || on_pass(&image).is_break() {
+                break;
             }
+        }
+        self.progress.finished();
+        image
+    }
 
-            coords
-        };
+    /// Add samples to every pixel of `image` until each has `end`, or is clean enough to stop.
+    /// If cancelled part way, `image` is left as it was when counting samples, so that
+    /// carrying on with it later will finish the pass.
+    fn sample(&self, image: &mut Accumulation, end: u32) {
+        let frame = self.frame();
+        let mut tiles = self.tile_order.tiles(frame, self.tile_size);
+        if self.shuffle {
+            tiles.shuffle(&mut Pcg64Mcg::seed_from_u64(self.seed));
+        }
 
-        let mut pixels: Vec<((u32, u32), Pixel)> = coords
-            .par_iter()
-            .progress_with(progress_bar(self.height as u64 * self.width as u64))
-            .map(|&(j, i)| {
-                let col = (0..self.samples_per_pixel)
-                    .map(|_| {
-                        let u = (i as f64 + random::<f64>()) / (self.width - 1) as f64;
-                        let v = (j as f64 + random::<f64>()) / (self.height - 1) as f64;
-                        let r = self.camera.ray(u, v);
+        let before = &image.pixels;
+        // Bridging hands out tiles one at a time in order, rather than splitting the list
+        // between threads up front, so the image fills in the way `tile_order` says.
+        let pixels: Vec<(usize, Pixel)> = tiles
+            .into_iter()
+            .par_bridge()
+            .flat_map_iter(|tile| {
+                let pixels: Vec<(usize, Pixel)> = tile
+                    .pixels()
+                    .map(|(x, y)| {
+                        let index =
+                            (y - frame.y) as usize * frame.width as usize + (x - frame.x) as usize;
+                        // Carry on from the pixel's running total, so that passes add up
+                        // exactly as one long pass would.
+                        let mut pixel = before[index];
+                        let (i, j) = (x, self.height - 1 - y);
+                        while pixel.samples < end
+                            && !self.converged(&pixel)
+                            && !self.cancel.is_cancelled()
+                        {
+                            let mut rng = self.sample_rng(i, j, pixel.samples);
+                            let u = (i as f64 + rng.gen::<f64>()) / (self.width - 1) as f64;
+                            let v = (j as f64 + rng.gen::<f64>()) / (self.height - 1) as f64;
+                            let r = self.camera.ray(u, v, &mut rng);
 
-                        ray_colour(r, self.scene.as_ref(), self.bounce_depth)
+                            pixel.add(self.integrator.radiance(self, r, &mut rng));
+                        }
+                        (index, pixel)
                     })
-                    .sum::<Pixel>();
-                ((i, j), col)
+                    .collect();
+                let samples = pixels
+                    .iter()
+                    .map(|(index, pixel)| (pixel.samples - before[*index].samples) as u64)
+                    .sum();
+                self.progress.advanced(pixels.len() as u64, samples);
+                pixels
             })
             .collect();
 
-        if self.shuffle {
-            pixels.par_sort_unstable_by_key(|((i, j), _)| (self.height - j) * self.width + i);
+        for (index, pixel) in pixels {
+            image.pixels[index] = pixel;
+        }
+        if !self.cancel.is_cancelled() {
+            image.samples = end;
         }
+    }
 
-        pixels.iter().map(|&(_, p)| p.resolve(self.gamma)).collect()
+    /// Whether adaptive sampling is done with `pixel`. Checked after every sample, so where
+    /// a pixel stops doesn't depend on how the samples are split into passes.
+    fn converged(&self, pixel: &Pixel) -> bool {
+        match self.adaptive {
+            Some(adaptive) => {
+                pixel.samples >= adaptive.min_samples.max(2)
+                    && pixel.relative_error() <= adaptive.threshold
+            }
+            None => false,
+        }
     }
-}
 
-fn ray_colour<C: Collider>(r: Ray, scene: C, depth: u32) -> Colour {
-    if depth == 0 {
-        return Colour::new(0.0, 0.0, 0.0);
+    /// The random number stream for one sample of one pixel, which depends only on the seed,
+    /// the pixel's position and which sample it is, so that it doesn't matter which thread
+    /// takes the sample, or when.
+    fn sample_rng(&self, i: u32, j: u32, sample: u32) -> Pcg64Mcg {
+        let index = j as u64 * self.width as u64 + i as u64;
+        // Spread neighbouring pixels' seeds apart with the golden ratio, as in SplitMix64.
+        let pixel = self
+            .seed
+            .wrapping_add(index.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        Pcg64Mcg::seed_from_u64(mix(mix(pixel) ^ sample as u64))
     }
+}
+
+/// Settings for adaptive sampling, which takes more samples where the image is noisier.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Adaptive {
+    /// How much noise to leave: a pixel is done once the standard error of its mean brightness
+    /// is at most this fraction of the brightness. Dark pixels are judged against a brightness
+    /// of at least 0.01, so that they aren't sampled forever to resolve tiny differences.
+    pub threshold: f64,
+    /// Samples every pixel takes before it can stop, so that one with only a rare bright path
+    /// doesn't look clean just because that path hasn't been found yet.
+    pub min_samples: u32,
+}
 
-    if let Some(c) = scene.collide(r, (0.001, f64::INFINITY)) {
-        return if let Some((attenuation, scattered)) = c.scatter(r) {
-            ray_colour(scattered, scene, depth - 1).scale(attenuation)
-        } else {
-            Colour::new(0.0, 0.0, 0.0)
-        };
+impl Adaptive {
+    pub fn new(threshold: f64) -> Self {
+        Adaptive {
+            threshold,
+            min_samples: 16,
+        }
     }
+}
 
-    let t = (r.dir.unit().y + 1.0) / 2.0;
-    Blend(Colour::WHITE, Colour::new(0.5, 0.7, 1.0)).at(t)
+/// The SplitMix64 finaliser, which spreads every bit of its input over its output.
+pub(crate) fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
 
-fn progress_bar(len: u64) -> ProgressBar {
-    let bar = ProgressBar::new(len);
-    bar.set_style(
-        ProgressStyle::default_bar()
-            .template("{percent:>3}%▕{bar:40}▏ [{eta}/{elapsed}, {per_sec}]")
-            .progress_chars("█▉▊▋▌▍▎▏ "),
-    );
-    bar.set_draw_delta(len / 1000);
-    bar
+/// The samples taken so far for every pixel of an image, in rows from top to bottom.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Accumulation {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// How many samples every pixel has had, or was allowed, when sampling adaptively.
+    pub(crate) samples: u32,
+    pub(crate) pixels: Vec<Pixel>,
+}
+
+impl Accumulation {
+    pub fn new(width: u32, height: u32) -> Self {
+        Accumulation {
+            width,
+            height,
+            samples: 0,
+            pixels: vec![Pixel::default(); width as usize * height as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// How many samples each pixel has had so far. With adaptive sampling this is the most
+    /// any pixel could have had, and clean pixels will have stopped short of it.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// How many samples each pixel actually took.
+    pub fn sample_counts(&self) -> Vec<u32> {
+        self.pixels.iter().map(|p| p.samples).collect()
+    }
+
+    /// The number of samples each pixel took as a colour, from blue for none to red for
+    /// [`samples`](Accumulation::samples), to show where adaptive sampling worked hardest.
+    pub fn sample_map(&self) -> Vec<Colour> {
+        let most = self.samples.max(1) as f64;
+        self.pixels
+            .iter()
+            .map(|p| heatmap(p.samples as f64 / most))
+            .collect()
+    }
+
+    /// Copy the pixels of `tile` over this image, with its top left corner at `x`, `y`.
+    pub fn paste(&mut self, x: u32, y: u32, tile: &Accumulation) {
+        let at = Region::new(x, y, tile.width, tile.height);
+        assert!(
+            at.fits(self.width, self.height),
+            "tile {:?} isn't inside the {}x{} image",
+            at,
+            self.width,
+            self.height
+        );
+        at.paste(&mut self.pixels, self.width, &tile.pixels);
+        self.samples = self.samples.max(tile.samples);
+    }
+
+    /// The image so far as linear radiance. Pixels with no samples yet are black.
+    pub fn linear(&self) -> Vec<Colour> {
+        self.pixels.iter().map(Pixel::mean).collect()
+    }
+
+    /// The image so far, passed through `display` to be ready to show.
+    pub fn resolve(&self, display: &DisplayTransform) -> Vec<Colour> {
+        self.pixels
+            .iter()
+            .map(|p| display.apply(p.mean()))
+            .collect()
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
-struct Pixel {
-    colour: Colour,
-    samples: u32,
+pub(crate) struct Pixel {
+    pub(crate) colour: Colour,
+    pub(crate) samples: u32,
+    /// Running mean of the samples' luminance, and sum of their squared differences from it,
+    /// updated with Welford's algorithm.
+    pub(crate) brightness: f64,
+    pub(crate) m2: f64,
 }
 
-impl Pixel {
-    pub fn resolve(&self, gamma: f64) -> Colour {
-        let f = |v: f64| (v / self.samples as f64).powf(gamma.recip());
-        Colour {
-            r: f(self.colour.r),
-            g: f(self.colour.g),
-            b: f(self.colour.b),
+impl Default for Pixel {
+    fn default() -> Self {
+        Pixel {
+            colour: Colour::ZERO,
+            samples: 0,
+            brightness: 0.0,
+            m2: 0.0,
         }
     }
 }
 
-impl Sum<Colour> for Pixel {
-    fn sum<I>(iter: I) -> Self
-    where
-        I: Iterator<Item = Colour>,
-    {
-        iter.fold(
-            Pixel {
-                colour: Colour::ZERO,
-                samples: 0,
-            },
-            |mut p, c| {
-                p.colour += c;
-                p.samples += 1;
-                p
-            },
-        )
+impl Pixel {
+    pub fn mean(&self) -> Colour {
+        if self.samples == 0 {
+            return Colour::BLACK;
+        }
+        self.colour / self.samples
+    }
+
+    fn add(&mut self, sample: Colour) {
+        self.colour += sample;
+        self.samples += 1;
+
+        let luminance = sample.luminance();
+        let delta = luminance - self.brightness;
+        self.brightness += delta / self.samples as f64;
+        self.m2 += delta * (luminance - self.brightness);
+    }
+
+    /// The sample variance of the luminance.
+    fn variance(&self) -> f64 {
+        if self.samples < 2 {
+            return f64::INFINITY;
+        }
+        self.m2 / (self.samples - 1) as f64
+    }
+
+    /// The standard error of the mean luminance, as a fraction of it.
+    fn relative_error(&self) -> f64 {
+        (self.variance() / self.samples as f64).sqrt() / self.brightness.max(0.01)
     }
 }
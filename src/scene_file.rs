@@ -0,0 +1,707 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    load_obj, mix, scene_id, scenes::quad, Atmosphere, Background, Bvh, Camera, Colour, Dielectric,
+    DiffuseLight, DisplayTransform, EnvironmentMap, Gradient, HenyeyGreenstein, Lambertian, Light,
+    Lights, Material, Metal, ObjError, Raytracer, Scene, Sphere, ToneMap, Transfer, Triangle, Vec3,
+    Warning,
+};
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io {
+        file: PathBuf,
+        source: io::Error,
+    },
+    /// The file isn't valid TOML, or doesn't have the expected shape.
+    Parse {
+        file: PathBuf,
+        /// The line the error is on and the key there, when the parser says where it is.
+        at: Option<(usize, String)>,
+        source: Box<toml::de::Error>,
+    },
+    /// A value in the file is well-formed but can't be used.
+    Invalid {
+        file: PathBuf,
+        key: String,
+        message: String,
+    },
+    Obj {
+        file: PathBuf,
+        key: String,
+        source: ObjError,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io { file, source } => write!(f, "{}: {}", file.display(), source),
+            SceneError::Parse {
+                file,
+                at: Some((line, key)),
+                source,
+            } => write!(
+                f,
+                "{}:{}: `{}`: {}",
+                file.display(),
+                line,
+                key,
+                source.message()
+            ),
+            SceneError::Parse { file, source, .. } => write!(f, "{}: {}", file.display(), source),
+            SceneError::Invalid { file, key, message } => {
+                write!(f, "{}: `{}` {}", file.display(), key, message)
+            }
+            SceneError::Obj { file, key, source } => {
+                write!(
+                    f,
+                    "{}: `{}` can't be loaded: {}",
+                    file.display(),
+                    key,
+                    source
+                )
+            }
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io { source, .. } => Some(source),
+            SceneError::Parse { source, .. } => Some(source),
+            SceneError::Invalid { .. } => None,
+            SceneError::Obj { source, .. } => Some(source),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    render: RenderSettings,
+    camera: CameraSettings,
+    background: Option<BackgroundSettings>,
+    atmosphere: Option<AtmosphereSettings>,
+    #[serde(default)]
+    materials: BTreeMap<String, MaterialSettings>,
+    #[serde(default)]
+    objects: Vec<ObjectSettings>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RenderSettings {
+    width: u32,
+    height: u32,
+    #[serde(default = "RenderSettings::default_samples")]
+    samples_per_pixel: u32,
+    #[serde(default = "RenderSettings::default_depth")]
+    bounce_depth: u32,
+    /// Defaults to the sRGB curve.
+    gamma: Option<f64>,
+    #[serde(default)]
+    exposure: f64,
+    #[serde(default)]
+    tone_map: ToneMapSettings,
+    /// For `extended_reinhard`.
+    #[serde(default = "RenderSettings::default_white")]
+    white: f64,
+    #[serde(default)]
+    shuffle: bool,
+    #[serde(default)]
+    seed: u64,
+}
+
+impl RenderSettings {
+    fn default_samples() -> u32 {
+        100
+    }
+
+    fn default_depth() -> u32 {
+        50
+    }
+
+    fn default_white() -> f64 {
+        4.0
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+enum ToneMapSettings {
+    #[default]
+    Clamp,
+    Reinhard,
+    ExtendedReinhard,
+    Aces,
+    Hable,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraSettings {
+    origin: [f64; 3],
+    target: [f64; 3],
+    #[serde(default = "CameraSettings::default_vup")]
+    vup: [f64; 3],
+    /// In degrees.
+    v_fov: f64,
+    /// Defaults to the ratio of the image's width and height.
+    aspect_ratio: Option<f64>,
+    #[serde(default)]
+    aperture: f64,
+    /// Defaults to the distance from `origin` to `target`.
+    focus_dist: Option<f64>,
+}
+
+impl CameraSettings {
+    fn default_vup() -> [f64; 3] {
+        [0.0, 1.0, 0.0]
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum BackgroundSettings {
+    Colour {
+        colour: [f64; 3],
+    },
+    Gradient {
+        bottom: [f64; 3],
+        top: [f64; 3],
+    },
+    Environment {
+        path: PathBuf,
+        /// In degrees.
+        #[serde(default)]
+        rotation: f64,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AtmosphereSettings {
+    density: f64,
+    #[serde(default = "AtmosphereSettings::default_albedo")]
+    albedo: [f64; 3],
+    /// Henyey-Greenstein asymmetry.
+    #[serde(default)]
+    g: f64,
+    extent: Option<f64>,
+}
+
+impl AtmosphereSettings {
+    fn default_albedo() -> [f64; 3] {
+        [1.0, 1.0, 1.0]
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialSettings {
+    Lambertian {
+        albedo: [f64; 3],
+    },
+    Metal {
+        albedo: [f64; 3],
+        #[serde(default)]
+        fuzz: f64,
+    },
+    Dielectric {
+        eta: f64,
+    },
+    DiffuseLight {
+        emit: [f64; 3],
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ObjectSettings {
+    Sphere {
+        centre: [f64; 3],
+        radius: f64,
+        material: String,
+    },
+    Triangle {
+        vertices: [[f64; 3]; 3],
+        material: String,
+    },
+    /// A parallelogram with one corner at `origin` and sides `u` and `v`.
+    Quad {
+        origin: [f64; 3],
+        u: [f64; 3],
+        v: [f64; 3],
+        material: String,
+    },
+    /// A Wavefront OBJ model, with its own MTL materials.
+    Obj { path: PathBuf },
+}
+
+fn vec3([x, y, z]: [f64; 3]) -> Vec3 {
+    Vec3::new(x, y, z)
+}
+
+fn colour([r, g, b]: [f64; 3]) -> Colour {
+    Colour::new(r, g, b)
+}
+
+/// Load a TOML scene description, returning a [`Raytracer`] ready to render it. Paths in the
+/// file are relative to the file itself. Spheres, triangles and quads made of `diffuse_light`
+/// are also the scene's [`Lights`]. Anything [`load_obj`] warns about in the files the scene
+/// refers to is returned alongside.
+///
+/// ```toml
+/// [render]
+/// width = 300
+/// height = 200
+/// samples_per_pixel = 100  # optional, default 100
+/// bounce_depth = 50        # optional, default 50
+/// gamma = 2.2              # optional, default the sRGB curve
+/// exposure = 0             # optional, default 0, in stops
+/// tone_map = "aces"        # optional, default "clamp", or "reinhard", "extended_reinhard"
+/// white = 4.0              #   with `white`, or "hable"
+/// shuffle = false          # optional, default false
+/// seed = 0                 # optional, default 0
+///
+/// [camera]
+/// origin = [13, 2, 3]
+/// target = [0, 0, 0]
+/// vup = [0, 1, 0]          # optional, default straight up
+/// v_fov = 20               # degrees
+/// aspect_ratio = 1.5       # optional, default width / height
+/// aperture = 0.1           # optional, default 0
+/// focus_dist = 10          # optional, default the distance to the target
+///
+/// [background]             # optional, default a pale blue sky
+/// type = "gradient"        # or "colour" with `colour`, or "environment" with `path`
+/// bottom = [1, 1, 1]       # and optional `rotation` in degrees
+/// top = [0.5, 0.7, 1.0]
+///
+/// [atmosphere]             # optional, default clear air
+/// density = 0.01           # chance of scattering per unit distance
+/// albedo = [1, 1, 1]       # optional, default white
+/// g = 0.0                  # optional, default 0 (isotropic), up to 1 for forward scattering
+/// extent = 100             # optional, default infinite, hiding the background
+///
+/// [materials.ground]
+/// type = "lambertian"      # or "metal" with `albedo` and `fuzz`, "dielectric" with `eta`,
+/// albedo = [0.5, 0.5, 0.5] # or "diffuse_light" with `emit`
+///
+/// [[objects]]
+/// type = "sphere"          # or "triangle" with `vertices`, "quad" with `origin`, `u` and
+/// centre = [0, -1000, 0]   # `v`, or "obj" with a `path` and no material
+/// radius = 1000
+/// material = "ground"
+/// ```
+pub fn load_scene(path: impl AsRef<Path>) -> Result<(Raytracer, Vec<Warning>), SceneError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|source| SceneError::Io {
+        file: path.to_owned(),
+        source,
+    })?;
+    let file: SceneFile = toml::from_str(&text).map_err(|source| SceneError::Parse {
+        file: path.to_owned(),
+        at: source.span().map(|span| locate(&text, span.start)),
+        source: Box::new(source),
+    })?;
+
+    let loader = Loader {
+        path,
+        warnings: RefCell::new(Vec::new()),
+        files: RefCell::new(Vec::new()),
+    };
+    let mut raytracer = loader.load(file)?;
+
+    // The scene is everything that was read to build it.
+    let mut id = scene_id(&text);
+    for file in loader.files.into_inner() {
+        let contents = fs::read(&file).map_err(|source| SceneError::Io { file, source })?;
+        id = mix(id ^ scene_id(&contents));
+    }
+    raytracer.scene_id = Some(id);
+    Ok((raytracer, loader.warnings.into_inner()))
+}
+
+/// The line `offset` is on in `text`, and the dotted key there: the key being set, or the
+/// table if the line is a table header or carries on a value from an earlier line. Tables in
+/// arrays are numbered, as in `objects[2].radius`.
+fn locate(text: &str, offset: usize) -> (usize, String) {
+    let before = &text[..offset.min(text.len())];
+    let number = before.matches('\n').count() + 1;
+    let line = text.lines().nth(number - 1).unwrap_or_default().trim();
+
+    // Lines inside a multi-line array can start with `[` too, but can't be only a key.
+    let is_key = |name: &str| {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || "_-. \"".contains(c))
+    };
+    let mut table = String::new();
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for header in text.lines().take(number).map(str::trim) {
+        if let Some(name) = header.strip_prefix("[[").and_then(|h| h.split("]]").next()) {
+            if is_key(name) {
+                let name = name.trim();
+                let count = counts.entry(name).or_default();
+                table = format!("{}[{}]", name, count);
+                *count += 1;
+            }
+        } else if let Some(name) = header.strip_prefix('[').and_then(|h| h.split(']').next()) {
+            if is_key(name) {
+                table = name.trim().to_owned();
+            }
+        }
+    }
+
+    let key = match line.split_once('=') {
+        Some((key, _)) if !line.starts_with('[') => key.trim().trim_matches('"'),
+        _ => return (number, table),
+    };
+    if table.is_empty() {
+        (number, key.to_owned())
+    } else {
+        (number, format!("{}.{}", table, key))
+    }
+}
+
+struct Loader<'a> {
+    path: &'a Path,
+    /// From the files the scene refers to.
+    warnings: RefCell<Vec<Warning>>,
+    /// Every file read besides the scene file.
+    files: RefCell<Vec<PathBuf>>,
+}
+
+impl Loader<'_> {
+    fn invalid(&self, key: impl Into<String>, message: impl Into<String>) -> SceneError {
+        SceneError::Invalid {
+            file: self.path.to_owned(),
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+
+    fn check(&self, ok: bool, key: &str, message: &str) -> Result<(), SceneError> {
+        if ok {
+            Ok(())
+        } else {
+            Err(self.invalid(key, message))
+        }
+    }
+
+    fn check_colour(&self, c: [f64; 3], key: &str) -> Result<Colour, SceneError> {
+        self.check(
+            c.iter().all(|&v| v >= 0.0 && v.is_finite()),
+            key,
+            "must not have negative or infinite components",
+        )?;
+        Ok(colour(c))
+    }
+
+    fn relative(&self, path: &Path) -> PathBuf {
+        self.path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(path)
+    }
+
+    fn load(&self, file: SceneFile) -> Result<Raytracer, SceneError> {
+        let render = &file.render;
+        self.check(render.width > 1, "render.width", "must be at least 2")?;
+        self.check(render.height > 1, "render.height", "must be at least 2")?;
+        self.check(
+            render.samples_per_pixel > 0,
+            "render.samples_per_pixel",
+            "must be at least 1",
+        )?;
+        if let Some(gamma) = render.gamma {
+            self.check(
+                gamma > 0.0 && gamma.is_finite(),
+                "render.gamma",
+                "must be positive",
+            )?;
+        }
+        self.check(
+            render.exposure.is_finite(),
+            "render.exposure",
+            "must be a number",
+        )?;
+        self.check(
+            render.white > 0.0 && render.white.is_finite(),
+            "render.white",
+            "must be positive",
+        )?;
+
+        let camera = self.camera(&file.camera, render.width as f64 / render.height as f64)?;
+        let materials = self.materials(&file.materials)?;
+        let (objects, lights) = self.objects(&file.objects, &file.materials, &materials)?;
+
+        let mut raytracer = Raytracer::new(
+            Arc::new(Bvh::new(objects)),
+            camera,
+            render.width,
+            render.height,
+            render.samples_per_pixel,
+            render.bounce_depth,
+        );
+        raytracer.lights = lights;
+        raytracer.display = DisplayTransform {
+            exposure: render.exposure,
+            tone_map: match render.tone_map {
+                ToneMapSettings::Clamp => ToneMap::Clamp,
+                ToneMapSettings::Reinhard => ToneMap::Reinhard,
+                ToneMapSettings::ExtendedReinhard => ToneMap::ExtendedReinhard {
+                    white: render.white,
+                },
+                ToneMapSettings::Aces => ToneMap::Aces,
+                ToneMapSettings::Hable => ToneMap::Hable,
+            },
+            transfer: render.gamma.map_or(Transfer::Srgb, Transfer::Gamma),
+        };
+        raytracer.shuffle = render.shuffle;
+        raytracer.seed = render.seed;
+        if let Some(background) = &file.background {
+            raytracer.background = self.background(background)?;
+        }
+        if let Some(atmosphere) = &file.atmosphere {
+            raytracer.atmosphere = Some(self.atmosphere(atmosphere)?);
+        }
+
+        Ok(raytracer)
+    }
+
+    fn camera(&self, cam: &CameraSettings, default_ratio: f64) -> Result<Camera, SceneError> {
+        let (origin, target, vup) = (vec3(cam.origin), vec3(cam.target), vec3(cam.vup));
+        let view = target - origin;
+        self.check(
+            !view.small(),
+            "camera.target",
+            "must be somewhere other than `camera.origin`",
+        )?;
+        self.check(
+            !vup.cross(view).small(),
+            "camera.vup",
+            "must not point along the line from `camera.origin` to `camera.target`",
+        )?;
+        self.check(
+            cam.v_fov > 0.0 && cam.v_fov < 180.0,
+            "camera.v_fov",
+            "must be between 0 and 180 degrees",
+        )?;
+        let aspect_ratio = cam.aspect_ratio.unwrap_or(default_ratio);
+        self.check(
+            aspect_ratio > 0.0 && aspect_ratio.is_finite(),
+            "camera.aspect_ratio",
+            "must be positive",
+        )?;
+        self.check(
+            cam.aperture >= 0.0 && cam.aperture.is_finite(),
+            "camera.aperture",
+            "must not be negative",
+        )?;
+        let focus_dist = cam.focus_dist.unwrap_or_else(|| view.length());
+        self.check(
+            focus_dist > 0.0 && focus_dist.is_finite(),
+            "camera.focus_dist",
+            "must be positive",
+        )?;
+
+        Camera::builder()
+            .origin(origin)
+            .target(target)
+            .vup(vup)
+            .v_fov(cam.v_fov.to_radians())
+            .aspect_ratio(aspect_ratio)
+            .aperture(cam.aperture)
+            .focus_dist(focus_dist)
+            .build()
+            .map_err(|e| self.invalid("camera", e))
+    }
+
+    fn background(
+        &self,
+        background: &BackgroundSettings,
+    ) -> Result<Arc<dyn Background + Send + Sync>, SceneError> {
+        Ok(match background {
+            BackgroundSettings::Colour { colour } => {
+                Arc::new(self.check_colour(*colour, "background.colour")?)
+            }
+            BackgroundSettings::Gradient { bottom, top } => Arc::new(Gradient::new(
+                self.check_colour(*bottom, "background.bottom")?,
+                self.check_colour(*top, "background.top")?,
+            )),
+            BackgroundSettings::Environment { path, rotation } => {
+                let path = self.relative(path);
+                let map = EnvironmentMap::open(&path).map_err(|e| {
+                    self.invalid("background.path", format!("can't be read: {}", e))
+                })?;
+                self.files.borrow_mut().push(path);
+                Arc::new(map.rotated(rotation.to_radians()))
+            }
+        })
+    }
+
+    fn atmosphere(&self, atmosphere: &AtmosphereSettings) -> Result<Atmosphere, SceneError> {
+        self.check(
+            atmosphere.density >= 0.0 && atmosphere.density.is_finite(),
+            "atmosphere.density",
+            "must not be negative",
+        )?;
+        self.check(
+            (-1.0..=1.0).contains(&atmosphere.g),
+            "atmosphere.g",
+            "must be between -1 and 1",
+        )?;
+        let albedo = self.check_colour(atmosphere.albedo, "atmosphere.albedo")?;
+
+        let mut result = Atmosphere::new(
+            atmosphere.density,
+            HenyeyGreenstein::new(albedo, atmosphere.g),
+        );
+        if let Some(extent) = atmosphere.extent {
+            self.check(extent > 0.0, "atmosphere.extent", "must be positive")?;
+            result.extent = extent;
+        }
+        Ok(result)
+    }
+
+    fn materials(
+        &self,
+        materials: &BTreeMap<String, MaterialSettings>,
+    ) -> Result<BTreeMap<String, Arc<dyn Material + Send + Sync>>, SceneError> {
+        materials
+            .iter()
+            .map(|(name, m)| {
+                let key = |field: &str| format!("materials.{}.{}", name, field);
+                let material: Arc<dyn Material + Send + Sync> = match *m {
+                    MaterialSettings::Lambertian { albedo } => {
+                        Arc::new(Lambertian::new(self.check_colour(albedo, &key("albedo"))?))
+                    }
+                    MaterialSettings::Metal { albedo, fuzz } => {
+                        self.check(
+                            (0.0..=1.0).contains(&fuzz),
+                            &key("fuzz"),
+                            "must be between 0 and 1",
+                        )?;
+                        Arc::new(Metal::new(self.check_colour(albedo, &key("albedo"))?, fuzz))
+                    }
+                    MaterialSettings::Dielectric { eta } => {
+                        self.check(
+                            eta > 0.0 && eta.is_finite(),
+                            &key("eta"),
+                            "must be positive",
+                        )?;
+                        Arc::new(Dielectric::new(eta))
+                    }
+                    MaterialSettings::DiffuseLight { emit } => {
+                        Arc::new(DiffuseLight::new(self.check_colour(emit, &key("emit"))?))
+                    }
+                };
+                Ok((name.clone(), material))
+            })
+            .collect()
+    }
+
+    /// Build the scene's objects, picking out those made of `diffuse_light` as its lights.
+    fn objects(
+        &self,
+        objects: &[ObjectSettings],
+        settings: &BTreeMap<String, MaterialSettings>,
+        materials: &BTreeMap<String, Arc<dyn Material + Send + Sync>>,
+    ) -> Result<(Scene, Lights), SceneError> {
+        let mut scene: Scene = Vec::new();
+        let mut lights: Lights = Vec::new();
+        let emissive = |name: &String| {
+            matches!(
+                settings.get(name),
+                Some(MaterialSettings::DiffuseLight { .. })
+            )
+        };
+
+        for (i, object) in objects.iter().enumerate() {
+            let key = |field: &str| format!("objects[{}].{}", i, field);
+            let material = |name: &String| {
+                materials.get(name).cloned().ok_or_else(|| {
+                    self.invalid(
+                        key("material"),
+                        format!("names unknown material `{}`", name),
+                    )
+                })
+            };
+
+            let (object, name): (Arc<dyn Light + Send + Sync>, _) = match object {
+                ObjectSettings::Sphere {
+                    centre,
+                    radius,
+                    material: name,
+                } => {
+                    self.check(
+                        *radius != 0.0 && radius.is_finite(),
+                        &key("radius"),
+                        "must not be zero",
+                    )?;
+                    let sphere = Sphere::new(vec3(*centre), *radius, Arc::new(material(name)?));
+                    (Arc::new(sphere), name)
+                }
+                ObjectSettings::Triangle {
+                    vertices,
+                    material: name,
+                } => {
+                    let [a, b, c] = vertices.map(vec3);
+                    self.check(
+                        !(b - a).cross(c - a).small(),
+                        &key("vertices"),
+                        "must not all lie on one line",
+                    )?;
+                    let triangle = Triangle::new([a, b, c], Arc::new(material(name)?));
+                    (Arc::new(triangle), name)
+                }
+                ObjectSettings::Quad {
+                    origin,
+                    u,
+                    v,
+                    material: name,
+                } => {
+                    let (o, u, v) = (vec3(*origin), vec3(*u), vec3(*v));
+                    self.check(
+                        !u.cross(v).small(),
+                        &key("v"),
+                        "must not be parallel to `u`",
+                    )?;
+                    (Arc::new(quad(o, u, v, Arc::new(material(name)?))), name)
+                }
+                ObjectSettings::Obj { path } => {
+                    let obj = load_obj(self.relative(path)).map_err(|source| SceneError::Obj {
+                        file: self.path.to_owned(),
+                        key: key("path"),
+                        source,
+                    })?;
+                    scene.extend(obj.scene);
+                    self.warnings.borrow_mut().extend(obj.warnings);
+                    self.files.borrow_mut().extend(obj.files);
+                    continue;
+                }
+            };
+
+            if emissive(name) {
+                lights.push(object.clone());
+            }
+            scene.push(Box::new(object));
+        }
+
+        Ok((scene, lights))
+    }
+}
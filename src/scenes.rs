@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use itertools::iproduct;
+use rand::Rng;
+
+use crate::{
+    Colour, Dielectric, DiffuseLight, Lambertian, Lights, Material, Metal, Scene, Sphere,
+    TriangleMesh, Vec3,
+};
+
+/// The final scene from _Ray Tracing in One Weekend_: three large spheres surrounded by a
+/// field of small ones, laid out using `rng`.
+pub fn random_scene<R: Rng + ?Sized>(rng: &mut R) -> Scene {
+    let mut world: Scene = Vec::new();
+
+    // Ground
+    let ground = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    world.push(Box::new(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground,
+    )));
+
+    let glass = Arc::new(Dielectric::new(1.5));
+
+    for (a, b) in iproduct!((-10..=10), (-10..=10)) {
+        let centre = Vec3::new(
+            a as f64 + 0.9 * rng.gen::<f64>(),
+            0.2,
+            b as f64 + 0.9 * rng.gen::<f64>(),
+        );
+
+        if (centre - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+            match rng.gen::<f64>() {
+                r if (0.0..0.8).contains(&r) => {
+                    let albedo = rng.gen();
+                    let mat = Arc::new(Lambertian::new(albedo));
+                    world.push(Box::new(Sphere::new(centre, 0.2, mat)));
+                }
+                r if (0.8..0.95).contains(&r) => {
+                    let albedo = rng.gen();
+                    let fuzz = rng.gen_range(0.0..0.5);
+                    let mat = Arc::new(Metal::new(albedo, fuzz));
+                    world.push(Box::new(Sphere::new(centre, 0.2, mat)));
+                }
+                r if (0.95..1.0).contains(&r) => {
+                    world.push(Box::new(Sphere::new(centre, 0.2, glass.clone())))
+                }
+                _ => unreachable!(),
+            };
+        }
+    }
+
+    world.push(Box::new(Sphere::new(
+        Vec3::new(0.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    world.push(Box::new(Sphere::new(
+        Vec3::new(-4.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Lambertian::new(Colour::new(0.4, 0.2, 0.1))),
+    )));
+    world.push(Box::new(Sphere::new(
+        Vec3::new(4.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Metal::new(Colour::new(0.7, 0.6, 0.5), 0.0)),
+    )));
+
+    world
+}
+
+/// The Cornell box: a 555-unit cube open at the front, lit only by a panel in the ceiling, with
+/// a tall box and a glass ball inside. Best viewed from (278, 278, -800) looking at
+/// (278, 278, 0) with a 40° field of view, against a black [`Background`](crate::Background).
+///
+/// Returns the ceiling panel as the scene's only light, as well as part of the scene.
+pub fn cornell_box() -> (Scene, Lights) {
+    let mut world: Scene = Vec::new();
+
+    let red = Arc::new(Lambertian::new(Colour::new(0.65, 0.05, 0.05)));
+    let white = Arc::new(Lambertian::new(Colour::new(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::new(Colour::new(0.12, 0.45, 0.15)));
+    let light = Arc::new(DiffuseLight::new(Colour::new(15.0, 15.0, 15.0)));
+
+    let (x, y, z) = (
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+    );
+    world.push(Box::new(quad(x, y, z, green)));
+    world.push(Box::new(quad(Vec3::ZERO, y, z, red)));
+    let light = Arc::new(quad(
+        Vec3::new(213.0, 554.0, 227.0),
+        Vec3::new(130.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 105.0),
+        light,
+    ));
+    world.push(Box::new(light.clone()));
+    world.push(Box::new(quad(Vec3::ZERO, x, z, white.clone())));
+    world.push(Box::new(quad(y, x, z, white.clone())));
+    world.push(Box::new(quad(z, x, y, white.clone())));
+
+    world.push(Box::new(cuboid(
+        Vec3::new(265.0, 0.0, 295.0),
+        Vec3::new(165.0, 330.0, 165.0),
+        f64::to_radians(15.0),
+        white,
+    )));
+    world.push(Box::new(Sphere::new(
+        Vec3::new(190.0, 90.0, 190.0),
+        90.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+
+    (world, vec![light])
+}
+
+/// A parallelogram with one corner at `origin` and sides `u` and `v`.
+pub(crate) fn quad<M: Material>(
+    origin: Vec3,
+    u: Vec3,
+    v: Vec3,
+    material: Arc<M>,
+) -> TriangleMesh<M> {
+    TriangleMesh::new(
+        vec![origin, origin + u, origin + u + v, origin + v],
+        vec![[0, 1, 2], [0, 2, 3]],
+        material,
+    )
+    .unwrap()
+}
+
+/// A box with one bottom corner at `corner` and the given size, turned by `angle` radians
+/// about the vertical axis through that corner.
+fn cuboid<M: Material>(corner: Vec3, size: Vec3, angle: f64, material: Arc<M>) -> TriangleMesh<M> {
+    let (sin, cos) = angle.sin_cos();
+    let positions = iproduct!([0.0, size.x], [0.0, size.y], [0.0, size.z])
+        .map(|(x, y, z)| corner + Vec3::new(cos * x + sin * z, y, -sin * x + cos * z))
+        .collect();
+    // Corners are numbered by which of x, y and z are at their far side, as bits 2, 1 and 0.
+    let faces = [
+        [0, 1, 3, 2],
+        [4, 6, 7, 5],
+        [0, 4, 5, 1],
+        [2, 3, 7, 6],
+        [0, 2, 6, 4],
+        [1, 5, 7, 3],
+    ];
+    let triangles = faces
+        .iter()
+        .flat_map(|&[a, b, c, d]| [[a, b, c], [a, c, d]])
+        .collect();
+    TriangleMesh::new(positions, triangles, material).unwrap()
+}
@@ -0,0 +1,334 @@
+use std::{path::Path, sync::Arc};
+
+use image::{
+    error::{ParameterError, ParameterErrorKind},
+    ImageError, ImageResult,
+};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{Blend, Colour, Vec3};
+
+/// A colour which varies over a surface.
+pub trait Texture {
+    /// The colour at texture coordinates `uv` and position `point` on a surface.
+    fn value(&self, uv: (f64, f64), point: Vec3) -> Colour;
+}
+
+impl Texture for Colour {
+    fn value(&self, _uv: (f64, f64), _point: Vec3) -> Colour {
+        *self
+    }
+}
+
+impl<T> Texture for Arc<T>
+where
+    T: Texture + ?Sized,
+{
+    fn value(&self, uv: (f64, f64), point: Vec3) -> Colour {
+        (**self).value(uv, point)
+    }
+}
+
+/// A texture shared between materials, chosen at runtime.
+pub type SharedTexture = Arc<dyn Texture + Send + Sync>;
+
+/// A 3D chequerboard of cubes `size` wide, alternating between two textures.
+#[derive(Clone)]
+pub struct Checker {
+    pub size: f64,
+    pub even: SharedTexture,
+    pub odd: SharedTexture,
+}
+
+impl Checker {
+    pub fn new(
+        size: f64,
+        even: impl Texture + Send + Sync + 'static,
+        odd: impl Texture + Send + Sync + 'static,
+    ) -> Self {
+        Checker {
+            size,
+            even: Arc::new(even),
+            odd: Arc::new(odd),
+        }
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, uv: (f64, f64), point: Vec3) -> Colour {
+        let cell = |v: f64| (v / self.size).floor() as i64;
+        if (cell(point.x) + cell(point.y) + cell(point.z)).rem_euclid(2) == 0 {
+            self.even.value(uv, point)
+        } else {
+            self.odd.value(uv, point)
+        }
+    }
+}
+
+const PERLIN_POINTS: usize = 256;
+
+/// Ken Perlin's gradient noise: smooth pseudo-random values between about -1 and 1 which
+/// change over distances of around 1.
+#[derive(Clone, Debug)]
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    /// Noise laid out using `rng`.
+    pub fn new<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let gradients = (0..PERLIN_POINTS).map(|_| Vec3::random_unit(rng)).collect();
+        let mut perm = || {
+            let mut p: Vec<usize> = (0..PERLIN_POINTS).collect();
+            p.shuffle(rng);
+            p
+        };
+        Perlin {
+            gradients,
+            perm_x: perm(),
+            perm_y: perm(),
+            perm_z: perm(),
+        }
+    }
+
+    pub fn noise(&self, p: Vec3) -> f64 {
+        let (fx, fy, fz) = (p.x.floor(), p.y.floor(), p.z.floor());
+        let (u, v, w) = (p.x - fx, p.y - fy, p.z - fz);
+        let (i, j, k) = (fx as i64, fy as i64, fz as i64);
+        let wrap = |n: i64| n.rem_euclid(PERLIN_POINTS as i64) as usize;
+
+        // Hermite smoothing hides the grid the gradients sit on.
+        let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+        let (uu, vv, ww) = (smooth(u), smooth(v), smooth(w));
+
+        let mut total = 0.0;
+        for (di, dj, dk) in itertools::iproduct!(0..2, 0..2, 0..2) {
+            let gradient = self.gradients
+                [self.perm_x[wrap(i + di)] ^ self.perm_y[wrap(j + dj)] ^ self.perm_z[wrap(k + dk)]];
+            let offset = Vec3::new(u - di as f64, v - dj as f64, w - dk as f64);
+            let weight = |d: i64, t: f64| if d == 1 { t } else { 1.0 - t };
+            total += weight(di, uu) * weight(dj, vv) * weight(dk, ww) * gradient.dot(offset);
+        }
+        total
+    }
+
+    /// The sum of `depth` octaves of noise, each at twice the frequency and half the strength
+    /// of the last, always positive.
+    pub fn turbulence(&self, p: Vec3, depth: u32) -> f64 {
+        let mut total = 0.0;
+        let (mut p, mut weight) = (p, 1.0);
+        for _ in 0..depth {
+            total += weight * self.noise(p);
+            weight /= 2.0;
+            p *= 2.0;
+        }
+        total.abs()
+    }
+}
+
+/// Perlin noise blended between two colours, with features around `1 / scale` across.
+#[derive(Clone, Debug)]
+pub struct Noise {
+    pub perlin: Perlin,
+    pub scale: f64,
+    pub low: Colour,
+    pub high: Colour,
+}
+
+impl Noise {
+    pub fn new(perlin: Perlin, scale: f64) -> Self {
+        Noise {
+            perlin,
+            scale,
+            low: Colour::BLACK,
+            high: Colour::WHITE,
+        }
+    }
+}
+
+impl Texture for Noise {
+    fn value(&self, _uv: (f64, f64), point: Vec3) -> Colour {
+        let t = (1.0 + self.perlin.noise(point * self.scale)) / 2.0;
+        Blend(self.low, self.high).at(t.clamp(0.0, 1.0))
+    }
+}
+
+/// Several octaves of Perlin noise, giving a rougher, cloudier look than [`Noise`].
+#[derive(Clone, Debug)]
+pub struct Turbulence {
+    pub perlin: Perlin,
+    pub scale: f64,
+    pub depth: u32,
+    pub low: Colour,
+    pub high: Colour,
+}
+
+impl Turbulence {
+    pub fn new(perlin: Perlin, scale: f64, depth: u32) -> Self {
+        Turbulence {
+            perlin,
+            scale,
+            depth,
+            low: Colour::BLACK,
+            high: Colour::WHITE,
+        }
+    }
+}
+
+impl Texture for Turbulence {
+    fn value(&self, _uv: (f64, f64), point: Vec3) -> Colour {
+        let t = self.perlin.turbulence(point * self.scale, self.depth);
+        Blend(self.low, self.high).at(t.clamp(0.0, 1.0))
+    }
+}
+
+/// Stripes along the z axis, `2π / scale` apart, warped by turbulence into veins like marble.
+#[derive(Clone, Debug)]
+pub struct Marble {
+    pub perlin: Perlin,
+    pub scale: f64,
+    /// How far the veins wander from straight stripes.
+    pub warp: f64,
+    pub vein: Colour,
+    pub stone: Colour,
+}
+
+impl Marble {
+    pub fn new(perlin: Perlin, scale: f64) -> Self {
+        Marble {
+            perlin,
+            scale,
+            warp: 10.0,
+            vein: Colour::BLACK,
+            stone: Colour::WHITE,
+        }
+    }
+}
+
+impl Texture for Marble {
+    fn value(&self, _uv: (f64, f64), point: Vec3) -> Colour {
+        let phase = self.scale * point.z + self.warp * self.perlin.turbulence(point, 7);
+        Blend(self.vein, self.stone).at((1.0 + phase.sin()) / 2.0)
+    }
+}
+
+/// What an [`ImageTexture`] shows outside texture coordinates 0 to 1.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum WrapMode {
+    /// Tile the image.
+    Repeat,
+    /// Tile the image, flipping every other copy so that the edges meet.
+    Mirror,
+    /// Stretch the pixels at the edges outwards.
+    Clamp,
+}
+
+impl WrapMode {
+    /// The pixel within `0..len` to use for pixel `i` of the infinite plane.
+    fn index(self, i: i64, len: usize) -> usize {
+        let len = len as i64;
+        let i = match self {
+            WrapMode::Repeat => i.rem_euclid(len),
+            WrapMode::Mirror => {
+                let i = i.rem_euclid(2 * len);
+                if i < len {
+                    i
+                } else {
+                    2 * len - 1 - i
+                }
+            }
+            WrapMode::Clamp => i.clamp(0, len - 1),
+        };
+        i as usize
+    }
+}
+
+/// An image stretched over texture coordinates 0 to 1, with `u` running from left to right
+/// and `v` from bottom to top, bilinearly filtered.
+#[derive(Clone, Debug)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Colour>,
+    pub wrap: WrapMode,
+}
+
+impl ImageTexture {
+    /// Build a texture from linear colours, in rows from top to bottom.
+    pub fn new(width: usize, height: usize, pixels: Vec<Colour>) -> Self {
+        assert!(width > 0 && height > 0, "image texture must not be empty");
+        assert_eq!(
+            width * height,
+            pixels.len(),
+            "image texture needs width × height pixels"
+        );
+        ImageTexture {
+            width,
+            height,
+            pixels,
+            wrap: WrapMode::Repeat,
+        }
+    }
+
+    /// Load a texture from an image file such as a PNG or JPEG, whose colours are taken to be
+    /// sRGB encoded.
+    pub fn open(path: impl AsRef<Path>) -> ImageResult<Self> {
+        let image = image::open(path)?.into_rgb32f();
+        if image.width() == 0 || image.height() == 0 {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        }
+        let pixels = image
+            .pixels()
+            .map(|p| {
+                let [r, g, b] = p.0.map(|c| srgb_to_linear(c as f64));
+                Colour::new(r, g, b)
+            })
+            .collect();
+        Ok(ImageTexture::new(
+            image.width() as usize,
+            image.height() as usize,
+            pixels,
+        ))
+    }
+
+    pub fn wrapped(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    fn pixel(&self, x: i64, y: i64) -> Colour {
+        let x = self.wrap.index(x, self.width);
+        let y = self.wrap.index(y, self.height);
+        self.pixels[y * self.width + x]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, (u, v): (f64, f64), _point: Vec3) -> Colour {
+        // Continuous pixel coordinates, with pixel centres at half-integers.
+        let x = u * self.width as f64 - 0.5;
+        let y = (1.0 - v) * self.height as f64 - 0.5;
+
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = Blend(self.pixel(x0, y0), self.pixel(x0 + 1, y0)).at(fx);
+        let bottom = Blend(self.pixel(x0, y0 + 1), self.pixel(x0 + 1, y0 + 1)).at(fx);
+        Blend(top, bottom).at(fy)
+    }
+}
+
+/// Undo the sRGB transfer function.
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
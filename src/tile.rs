@@ -0,0 +1,141 @@
+/// A rectangle of pixels, `x` across from the left of the image and `y` down from the top.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Region {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Region {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Whether the region is all within an image of the given size, and isn't empty.
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        self.width > 0
+            && self.height > 0
+            && self.x.checked_add(self.width).is_some_and(|x| x <= width)
+            && self.y.checked_add(self.height).is_some_and(|y| y <= height)
+    }
+
+    /// The pixels in the region, a row at a time from the top.
+    pub fn pixels(&self) -> impl Iterator<Item = (u32, u32)> {
+        let (x, y) = (self.x, self.y);
+        let (width, height) = (self.width, self.height);
+        (y..y + height).flat_map(move |y| (x..x + width).map(move |x| (x, y)))
+    }
+
+    /// Copy `crop`, an image of this region, over the same part of `image`, which is
+    /// `image_width` pixels wide, so that part of a render can be redone and put back.
+    pub fn paste<T: Copy>(&self, image: &mut [T], image_width: u32, crop: &[T]) {
+        assert_eq!(
+            crop.len(),
+            self.width as usize * self.height as usize,
+            "crop is the wrong size for the region"
+        );
+        let rows = crop.chunks_exact(self.width as usize);
+        for (y, row) in (self.y..).zip(rows) {
+            let start = y as usize * image_width as usize + self.x as usize;
+            image[start..start + row.len()].copy_from_slice(row);
+        }
+    }
+}
+
+/// The order to render tiles in.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum TileOrder {
+    /// Rows of tiles from the top, each from left to right.
+    #[default]
+    Scanline,
+    /// Outwards from the middle, where the subject usually is.
+    Spiral,
+    /// Along a Hilbert curve, which keeps each tile next to the one before, so the scene data
+    /// they need is likelier to still be in the cache.
+    Hilbert,
+}
+
+impl TileOrder {
+    /// Split `area` into tiles `size` pixels square, or smaller at its right and bottom edges,
+    /// in this order.
+    pub fn tiles(self, area: Region, size: u32) -> Vec<Region> {
+        let size = size.max(1);
+        let (across, down) = (area.width.div_ceil(size), area.height.div_ceil(size));
+        let tile = |(tx, ty): (u32, u32)| {
+            let (x, y) = (tx * size, ty * size);
+            Region::new(
+                area.x + x,
+                area.y + y,
+                size.min(area.width - x),
+                size.min(area.height - y),
+            )
+        };
+        let inside = |&(tx, ty): &(i64, i64)| {
+            (0..across as i64).contains(&tx) && (0..down as i64).contains(&ty)
+        };
+        let count = across as usize * down as usize;
+
+        let order: Vec<(u32, u32)> = match self {
+            TileOrder::Scanline => (0..down)
+                .flat_map(|ty| (0..across).map(move |tx| (tx, ty)))
+                .collect(),
+            TileOrder::Spiral => spiral((across as i64 / 2, down as i64 / 2))
+                .filter(inside)
+                .take(count)
+                .map(|(tx, ty)| (tx as u32, ty as u32))
+                .collect(),
+            TileOrder::Hilbert => {
+                let side = across.max(down).next_power_of_two();
+                (0..side as u64 * side as u64)
+                    .map(|d| hilbert(side, d))
+                    .filter(|&(tx, ty)| tx < across && ty < down)
+                    .collect()
+            }
+        };
+        order.into_iter().map(tile).collect()
+    }
+}
+
+/// Every point of the grid, walking outwards from `centre` in a square spiral.
+fn spiral(centre: (i64, i64)) -> impl Iterator<Item = (i64, i64)> {
+    const STEPS: [(i64, i64); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    // Legs of the spiral go 1, 1, 2, 2, 3, 3... steps, turning after each.
+    let legs = (0..).flat_map(|leg: usize| {
+        let (dx, dy) = STEPS[leg % 4];
+        std::iter::repeat_n((dx, dy), leg / 2 + 1)
+    });
+    std::iter::once(centre).chain(legs.scan(centre, |at, (dx, dy)| {
+        *at = (at.0 + dx, at.1 + dy);
+        Some(*at)
+    }))
+}
+
+/// The point `d` steps along a Hilbert curve filling a square grid `side` points across,
+/// which must be a power of two.
+fn hilbert(side: u32, d: u64) -> (u32, u32) {
+    let (mut x, mut y) = (0, 0);
+    let mut t = d;
+    let mut s = 1;
+    while s < side {
+        let rx = (t / 2) & 1;
+        let ry = (t ^ rx) & 1;
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx as u32;
+        y += s * ry as u32;
+        t /= 4;
+        s *= 2;
+    }
+    (x, y)
+}
@@ -0,0 +1,101 @@
+use crate::Colour;
+
+/// How to squeeze linear radiance, which can be as bright as it likes, into the 0 to 1 range
+/// a display can show.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum ToneMap {
+    /// Leave colours alone, so anything brighter than 1 is clipped.
+    #[default]
+    Clamp,
+    /// Reinhard's operator on luminance, `L / (1 + L)`, which never quite reaches white.
+    Reinhard,
+    /// Reinhard's operator extended so that luminance `white` and above maps to white.
+    ExtendedReinhard { white: f64 },
+    /// Narkowicz's fit of the ACES filmic curve, with a gentle toe and shoulder.
+    Aces,
+    /// John Hable's filmic curve from Uncharted 2.
+    Hable,
+}
+
+impl ToneMap {
+    pub fn apply(&self, c: Colour) -> Colour {
+        match *self {
+            ToneMap::Clamp => c,
+            ToneMap::Reinhard => scale_luminance(c, |l| l / (1.0 + l)),
+            ToneMap::ExtendedReinhard { white } => {
+                let w2 = white * white;
+                scale_luminance(c, |l| l * (1.0 + l / w2) / (1.0 + l))
+            }
+            ToneMap::Aces => per_channel(c, |x| {
+                let x = x.max(0.0);
+                (x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+            }),
+            ToneMap::Hable => {
+                // The curve as used in the game: doubled exposure, white at 11.2.
+                let white = hable(11.2);
+                per_channel(c, |x| hable(2.0 * x.max(0.0)) / white)
+            }
+        }
+    }
+}
+
+/// Scale a colour so that its luminance is `f` of what it was, keeping its hue.
+fn scale_luminance(c: Colour, f: impl Fn(f64) -> f64) -> Colour {
+    let l = c.luminance();
+    if l <= 0.0 {
+        return Colour::BLACK;
+    }
+    c * (f(l) / l)
+}
+
+fn per_channel(c: Colour, f: impl Fn(f64) -> f64) -> Colour {
+    Colour::new(f(c.r), f(c.g), f(c.b))
+}
+
+fn hable(x: f64) -> f64 {
+    const A: f64 = 0.15; // Shoulder strength
+    const B: f64 = 0.50; // Linear strength
+    const C: f64 = 0.10; // Linear angle
+    const D: f64 = 0.20; // Toe strength
+    const E: f64 = 0.02; // Toe numerator
+    const F: f64 = 0.30; // Toe denominator
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+}
+
+/// How tone mapped values are encoded for display.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum Transfer {
+    /// The sRGB curve: linear near black, then roughly a power of 1/2.4.
+    #[default]
+    Srgb,
+    /// Raise values to the power `1/gamma`.
+    Gamma(f64),
+}
+
+impl Transfer {
+    pub fn encode(&self, v: f64) -> f64 {
+        let v = v.max(0.0);
+        match *self {
+            Transfer::Srgb if v <= 0.0031308 => 12.92 * v,
+            Transfer::Srgb => 1.055 * v.powf(2.4f64.recip()) - 0.055,
+            Transfer::Gamma(gamma) => v.powf(gamma.recip()),
+        }
+    }
+}
+
+/// Everything done to linear radiance to make it ready to show: exposure, then tone mapping,
+/// then a transfer function. Values can still be above 1 afterwards, which encoders clip.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct DisplayTransform {
+    /// In stops: each 1 added doubles the brightness.
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+    pub transfer: Transfer,
+}
+
+impl DisplayTransform {
+    pub fn apply(&self, c: Colour) -> Colour {
+        let c = self.tone_map.apply(c * self.exposure.exp2());
+        per_channel(c, |v| self.transfer.encode(v))
+    }
+}
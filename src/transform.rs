@@ -0,0 +1,225 @@
+use std::ops::Mul;
+
+use crate::{Aabb, Ray, Vec3};
+
+type Matrix = [[f64; 4]; 4];
+
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// An affine transformation of space: any combination of translation, rotation, scaling and
+/// shearing, as a 4x4 matrix acting on column vectors. Keeps its inverse alongside, since
+/// transforming rays and normals needs both.
+///
+/// Transforms combine with `*` like matrices do, so `a * b` applies `b` first, then `a`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Transform {
+    matrix: Matrix,
+    inverse: Matrix,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        matrix: IDENTITY,
+        inverse: IDENTITY,
+    };
+
+    /// The transform with the given matrix, whose bottom row must be `[0, 0, 0, 1]`. `None` if
+    /// the matrix squashes space flat, and so can't be undone.
+    pub fn from_matrix(matrix: [[f64; 4]; 4]) -> Option<Self> {
+        let m = |r: usize, c: usize| matrix[r][c];
+        // Cofactors of the upper-left 3x3 block, giving its inverse over its determinant.
+        let cofactor = |r: usize, c: usize| {
+            let (r0, r1) = ((r + 1) % 3, (r + 2) % 3);
+            let (c0, c1) = ((c + 1) % 3, (c + 2) % 3);
+            m(r0, c0) * m(r1, c1) - m(r0, c1) * m(r1, c0)
+        };
+        let det = m(0, 0) * cofactor(0, 0) + m(0, 1) * cofactor(0, 1) + m(0, 2) * cofactor(0, 2);
+        if det.abs() < 1e-12 || matrix[3] != [0.0, 0.0, 0.0, 1.0] {
+            return None;
+        }
+
+        let mut inverse = IDENTITY;
+        for (r, row) in inverse.iter_mut().take(3).enumerate() {
+            for (c, v) in row.iter_mut().take(3).enumerate() {
+                *v = cofactor(c, r) / det;
+            }
+        }
+        // Undo the translation after undoing the rest.
+        for row in inverse.iter_mut().take(3) {
+            row[3] = -(0..3).map(|c| row[c] * m(c, 3)).sum::<f64>();
+        }
+        Some(Transform { matrix, inverse })
+    }
+
+    pub fn translate(offset: Vec3) -> Self {
+        let mut matrix = IDENTITY;
+        let mut inverse = IDENTITY;
+        for axis in 0..3 {
+            matrix[axis][3] = offset[axis];
+            inverse[axis][3] = -offset[axis];
+        }
+        Transform { matrix, inverse }
+    }
+
+    /// Scale by a different factor along each axis, none of which may be zero.
+    pub fn scale(factors: Vec3) -> Self {
+        let mut matrix = IDENTITY;
+        let mut inverse = IDENTITY;
+        for axis in 0..3 {
+            matrix[axis][axis] = factors[axis];
+            inverse[axis][axis] = factors[axis].recip();
+        }
+        Transform { matrix, inverse }
+    }
+
+    pub fn uniform_scale(factor: f64) -> Self {
+        Transform::scale(Vec3::new(factor, factor, factor))
+    }
+
+    /// Rotate anticlockwise by `angle` radians, looking back along `axis`.
+    pub fn rotate(axis: Vec3, angle: f64) -> Self {
+        let Vec3 { x, y, z } = axis.unit();
+        let (sin, cos) = angle.sin_cos();
+        let k = 1.0 - cos;
+        let matrix = [
+            [
+                cos + x * x * k,
+                x * y * k - z * sin,
+                x * z * k + y * sin,
+                0.0,
+            ],
+            [
+                y * x * k + z * sin,
+                cos + y * y * k,
+                y * z * k - x * sin,
+                0.0,
+            ],
+            [
+                z * x * k - y * sin,
+                z * y * k + x * sin,
+                cos + z * z * k,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        // Rotations are undone by their transpose.
+        Transform {
+            matrix,
+            inverse: transpose(&matrix),
+        }
+    }
+
+    pub fn rotate_x(angle: f64) -> Self {
+        Transform::rotate(Vec3::new(1.0, 0.0, 0.0), angle)
+    }
+
+    pub fn rotate_y(angle: f64) -> Self {
+        Transform::rotate(Vec3::new(0.0, 1.0, 0.0), angle)
+    }
+
+    pub fn rotate_z(angle: f64) -> Self {
+        Transform::rotate(Vec3::new(0.0, 0.0, 1.0), angle)
+    }
+
+    pub fn matrix(&self) -> [[f64; 4]; 4] {
+        self.matrix
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    pub fn point(&self, p: Vec3) -> Vec3 {
+        apply(&self.matrix, p, 1.0)
+    }
+
+    /// Transform a direction or offset, which unlike a point isn't moved by translation.
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        apply(&self.matrix, v, 0.0)
+    }
+
+    /// Transform a surface normal, so that it stays perpendicular to the transformed surface.
+    /// The result isn't a unit vector.
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        apply(&transpose(&self.inverse), n, 0.0)
+    }
+
+    /// Transform a ray, leaving its direction unnormalised so that distances along it are
+    /// measured in the same `t` before and after.
+    pub fn ray(&self, r: Ray) -> Ray {
+        Ray {
+            orig: self.point(r.orig),
+            dir: self.vector(r.dir),
+            ..r
+        }
+    }
+
+    /// The smallest axis-aligned box holding the transformed box.
+    pub fn aabb(&self, b: Aabb) -> Aabb {
+        let corner = |i: usize| {
+            let pick = |bit: usize, axis: usize| {
+                if i & bit == 0 {
+                    b.min[axis]
+                } else {
+                    b.max[axis]
+                }
+            };
+            self.point(Vec3::new(pick(1, 0), pick(2, 1), pick(4, 2)))
+        };
+        (1..8).fold(Aabb::new(corner(0), corner(0)), |acc, i| {
+            acc.grow(corner(i))
+        })
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::IDENTITY
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Transform) -> Transform {
+        Transform {
+            matrix: multiply(&self.matrix, &rhs.matrix),
+            inverse: multiply(&rhs.inverse, &self.inverse),
+        }
+    }
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = [[0.0; 4]; 4];
+    for (r, row) in out.iter_mut().enumerate() {
+        for (c, v) in row.iter_mut().enumerate() {
+            *v = (0..4).map(|k| a[r][k] * b[k][c]).sum();
+        }
+    }
+    out
+}
+
+fn transpose(m: &Matrix) -> Matrix {
+    let mut out = [[0.0; 4]; 4];
+    for (r, row) in out.iter_mut().enumerate() {
+        for (c, v) in row.iter_mut().enumerate() {
+            *v = m[c][r];
+        }
+    }
+    out
+}
+
+/// Multiply `(v, w)` by the matrix, dropping the fourth component of the result, which for an
+/// affine matrix is `w` again.
+fn apply(m: &Matrix, v: Vec3, w: f64) -> Vec3 {
+    let row = |r: usize| m[r][0] * v.x + m[r][1] * v.y + m[r][2] * v.z + m[r][3] * w;
+    Vec3::new(row(0), row(1), row(2))
+}
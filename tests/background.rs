@@ -0,0 +1,113 @@
+mod common;
+
+use std::f64::consts::PI;
+
+use rez::{Background, Colour, EnvironmentMap, Gradient, Vec3};
+
+use common::{assert_close, EPSILON};
+
+/// The direction at longitude `phi`, measured from -z towards +x, and `theta` down from +y.
+fn direction(phi: f64, theta: f64) -> Vec3 {
+    Vec3::new(
+        theta.sin() * phi.sin(),
+        theta.cos(),
+        -theta.sin() * phi.cos(),
+    )
+}
+
+/// A map with a different colour in every pixel.
+fn numbered(width: usize, height: usize) -> EnvironmentMap {
+    let pixels = (0..width * height)
+        .map(|i| Colour::new(i as f64, (i % width) as f64, (i / width) as f64))
+        .collect();
+    EnvironmentMap::new(width, height, pixels)
+}
+
+#[test]
+fn gradient_runs_from_bottom_to_top() {
+    let (bottom, top) = (Colour::new(1.0, 0.5, 0.0), Colour::new(0.0, 0.5, 1.0));
+    let sky = Gradient::new(bottom, top);
+
+    assert_close(sky.radiance(Vec3::new(0.0, -3.0, 0.0)), bottom, EPSILON);
+    assert_close(sky.radiance(Vec3::new(0.0, 0.5, 0.0)), top, EPSILON);
+    assert_close(
+        sky.radiance(Vec3::new(2.0, 0.0, -1.0)),
+        Colour::new(0.5, 0.5, 0.5),
+        EPSILON,
+    );
+    // The colour follows the height of the direction, not its angle.
+    let up = direction(1.0, PI / 3.0);
+    assert_close(sky.radiance(up), Colour::new(0.25, 0.5, 0.75), EPSILON);
+}
+
+#[test]
+fn texel_centres_map_to_their_pixels() {
+    let (width, height) = (8, 4);
+    let map = numbered(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let phi = ((x as f64 + 0.5) / width as f64 - 0.5) * 2.0 * PI;
+            let theta = (y as f64 + 0.5) / height as f64 * PI;
+            let i = y * width + x;
+            let expected = Colour::new(i as f64, x as f64, y as f64);
+            assert_close(map.radiance(direction(phi, theta) * 3.0), expected, EPSILON);
+        }
+    }
+
+    // Straight ahead is the middle of the image, between four pixel centres.
+    assert_close(
+        map.radiance(Vec3::new(0.0, 0.0, -1.0)),
+        Colour::new(15.5, 3.5, 1.5),
+        EPSILON,
+    );
+}
+
+#[test]
+fn poles_take_the_edge_rows() {
+    let top = Colour::new(1.0, 0.0, 0.0);
+    let bottom = Colour::new(0.0, 0.0, 1.0);
+    let map = EnvironmentMap::new(3, 2, vec![top, top, top, bottom, bottom, bottom]);
+
+    assert_close(map.radiance(Vec3::new(0.0, 1.0, 0.0)), top, EPSILON);
+    assert_close(map.radiance(Vec3::new(0.0, -1.0, 0.0)), bottom, EPSILON);
+    // Near the poles, rows aren't blended with anything beyond the image.
+    assert_close(map.radiance(direction(2.0, 0.01)), top, EPSILON);
+    assert_close(map.radiance(direction(-1.0, PI - 0.01)), bottom, EPSILON);
+}
+
+#[test]
+fn seam_wraps_around() {
+    let map = numbered(4, 1);
+
+    // Straight behind is on the seam, halfway between the last pixel and the first.
+    assert_close(
+        map.radiance(Vec3::new(0.0, 0.0, 1.0)),
+        Colour::new(1.5, 1.5, 0.0),
+        EPSILON,
+    );
+
+    // Either side of the seam, the colours meet.
+    let theta = PI / 2.0;
+    let (left, right) = (
+        map.radiance(direction(PI - 1e-9, theta)),
+        map.radiance(direction(-PI + 1e-9, theta)),
+    );
+    assert!((left.r - right.r).abs() < 1e-6, "{:?} {:?}", left, right);
+}
+
+#[test]
+fn rotation_turns_the_map() {
+    let map = numbered(4, 1);
+    let turned = numbered(4, 1).rotated(PI / 2.0);
+    for phi in [0.0, 0.3, 2.0, -1.0] {
+        let dir = direction(phi, PI / 2.0);
+        let expected = map.radiance(direction(phi - PI / 2.0, PI / 2.0));
+        assert_close(turned.radiance(dir), expected, EPSILON);
+    }
+}
+
+#[test]
+#[should_panic(expected = "environment map must not be empty")]
+fn empty_maps_are_refused() {
+    EnvironmentMap::new(0, 0, vec![]);
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use rez::{Bvh, Collider, Colour, Lambertian, Ray, Scene, Sphere, Vec3};
+
+fn spheres(rng: &mut StdRng, n: usize) -> Vec<(Vec3, f64)> {
+    (0..n)
+        .map(|_| {
+            let centre = Vec3::new(
+                rng.gen_range(-20.0..20.0),
+                rng.gen_range(-20.0..20.0),
+                rng.gen_range(-20.0..20.0),
+            );
+            (centre, rng.gen_range(0.05..2.0))
+        })
+        .collect()
+}
+
+fn scene(spheres: &[(Vec3, f64)]) -> Scene {
+    let mat = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    spheres
+        .iter()
+        .map(|&(c, r)| -> Box<dyn Collider + Send + Sync> {
+            Box::new(Sphere::new(c, r, mat.clone()))
+        })
+        .collect()
+}
+
+#[test]
+fn bvh_matches_brute_force() {
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    let spheres = spheres(&mut rng, 500);
+
+    let flat = scene(&spheres);
+    let bvh = Bvh::new(scene(&spheres));
+    assert_eq!(bvh.len(), flat.len());
+    assert_eq!(bvh.bounding_box(), flat.bounding_box());
+
+    let mut hits = 0;
+    for _ in 0..20_000 {
+        let orig = Vec3::new(
+            rng.gen_range(-30.0..30.0),
+            rng.gen_range(-30.0..30.0),
+            rng.gen_range(-30.0..30.0),
+        );
+        let ray = Ray::new(orig, Vec3::random_unit(&mut rng));
+
+        let expected = flat.collide(ray, (0.001, f64::INFINITY));
+        let actual = bvh.collide(ray, (0.001, f64::INFINITY));
+        match (expected, actual) {
+            (None, None) => {}
+            (Some(e), Some(a)) => {
+                hits += 1;
+                assert_eq!(e.t, a.t);
+                assert_eq!(e.point, a.point);
+                assert_eq!(e.normal, a.normal);
+                assert_eq!(e.front, a.front);
+            }
+            (e, a) => panic!(
+                "brute force hit {:?} but bvh hit {:?}",
+                e.map(|c| c.t),
+                a.map(|c| c.t)
+            ),
+        }
+    }
+    assert!(hits > 1000, "too few rays hit anything: {}", hits);
+}
+
+#[test]
+fn bvh_respects_t_range() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let spheres = spheres(&mut rng, 100);
+
+    let flat = scene(&spheres);
+    let bvh = Bvh::new(scene(&spheres));
+
+    for _ in 0..5_000 {
+        let ray = Ray::new(Vec3::ZERO, Vec3::random_unit(&mut rng));
+        let range = (rng.gen_range(0.0..10.0), rng.gen_range(10.0..40.0));
+        assert_eq!(
+            flat.collide(ray, range).map(|c| c.t),
+            bvh.collide(ray, range).map(|c| c.t)
+        );
+    }
+}
+
+#[test]
+fn empty_bvh() {
+    let bvh = Bvh::new(Vec::new());
+    let ray = Ray::new(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0));
+    assert!(bvh.collide(ray, (0.0, f64::INFINITY)).is_none());
+    assert!(bvh.bounding_box().is_none());
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use std::ops::ControlFlow;
+
+use rez::{scene_id, CheckpointError, Raytracer};
+
+use common::random_raytracer;
+
+fn raytracer(layout: u64) -> Raytracer {
+    random_raytracer(layout, 24, 16, 9)
+}
+
+fn stopped_after_one_pass(r: &Raytracer) -> Vec<u8> {
+    let image = r.render_progressive(4, |_| ControlFlow::Break(()));
+    assert_eq!(image.samples(), 4);
+    let mut saved = Vec::new();
+    r.checkpoint(&image, &mut saved).unwrap();
+    saved
+}
+
+#[test]
+fn resumed_render_matches_uninterrupted_one() {
+    let r = raytracer(3);
+    let saved = stopped_after_one_pass(&r);
+
+    let image = r.resume(saved.as_slice()).unwrap();
+    assert_eq!(image.samples(), 4);
+    let finished = r.render_from(image, 4, |_| ControlFlow::Continue(()));
+    assert_eq!(finished.samples(), 9);
+    assert_eq!(finished.linear(), r.render_linear());
+}
+
+#[test]
+fn resuming_another_scene_is_refused() {
+    let saved = stopped_after_one_pass(&raytracer(3));
+
+    let mismatch = |r: Raytracer| match r.resume(saved.as_slice()) {
+        Err(CheckpointError::Mismatch(message)) => message,
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("resumed a checkpoint of something else"),
+    };
+    assert!(mismatch(raytracer(4)).contains("different scene"));
+
+    let mut reseeded = raytracer(3);
+    reseeded.seed = 1;
+    assert!(mismatch(reseeded).contains("different scene"));
+
+    let mut resized = raytracer(3);
+    resized.width = 30;
+    assert_eq!(
+        mismatch(resized),
+        "the checkpoint is 24x16 pixels, not 30x16"
+    );
+
+    // More samples are fine, to carry on a finished render.
+    let mut more = raytracer(3);
+    more.samples_per_pixel = 100;
+    assert!(more.resume(saved.as_slice()).is_ok());
+}
+
+#[test]
+fn damaged_checkpoints_are_refused() {
+    let r = raytracer(3);
+    let saved = stopped_after_one_pass(&r);
+
+    let truncated = &saved[..saved.len() - 1];
+    assert!(matches!(r.resume(truncated), Err(CheckpointError::Format)));
+    assert!(matches!(
+        r.resume(&b"P3\n24 16\n255\n"[..]),
+        Err(CheckpointError::Format)
+    ));
+}
+
+#[test]
+fn fingerprints_come_from_the_description() {
+    assert_eq!(scene_id("cornell 7"), scene_id("cornell 7"));
+    assert_ne!(scene_id("cornell 7"), scene_id("cornell 8"));
+    // Not only the same each run, but on every machine and with every build.
+    assert_eq!(scene_id("cornell 7"), 11749721338509677142);
+    assert_eq!(raytracer(3).fingerprint(), Some(14460805935614669852));
+
+    let mut renamed = raytracer(3);
+    renamed.scene_id = Some(scene_id("something else"));
+    assert_ne!(renamed.fingerprint(), raytracer(3).fingerprint());
+}
+
+#[test]
+fn scenes_without_an_id_are_not_checkpointed() {
+    let r = raytracer(3);
+    let saved = stopped_after_one_pass(&r);
+
+    let mut unidentified = raytracer(3);
+    unidentified.scene_id = None;
+    assert_eq!(unidentified.fingerprint(), None);
+    assert!(matches!(
+        unidentified.resume(saved.as_slice()),
+        Err(CheckpointError::Unidentified)
+    ));
+
+    let image = unidentified.render_progressive(1, |_| ControlFlow::Break(()));
+    let e = unidentified.checkpoint(&image, Vec::new()).unwrap_err();
+    assert!(matches!(
+        e.into_inner().unwrap().downcast_ref(),
+        Some(CheckpointError::Unidentified)
+    ));
+}
@@ -0,0 +1,338 @@
+use std::{
+    env, fs,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    process::{Command, Output, Stdio},
+};
+
+fn rez(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rez"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+fn read_u32(stream: &mut TcpStream) -> u32 {
+    let mut bytes = [0; 4];
+    stream.read_exact(&mut bytes).unwrap();
+    u32::from_le_bytes(bytes)
+}
+
+#[test]
+fn help_lists_subcommands() {
+    let output = rez(&["--help"]);
+    assert!(output.status.success());
+
+    let help = stdout(&output);
+    assert!(help.starts_with("A physically based path tracer\n\nUsage: rez <COMMAND>\n"));
+    for line in [
+        "  render  Render a scene to an image",
+        "  check   Check a scene file for errors without rendering it",
+    ] {
+        assert!(help.contains(line), "missing {:?} in:\n{}", line, help);
+    }
+}
+
+#[test]
+fn render_help_lists_options() {
+    let output = rez(&["render", "--help"]);
+    assert!(output.status.success());
+
+    let help = stdout(&output);
+    assert!(help.contains("Usage: rez render [OPTIONS] <OUTPUT>"));
+    for flag in [
+        "-s, --scene <SCENE>",
+        "-b, --builtin <BUILTIN>",
+        "-W, --width <WIDTH>",
+        "-H, --height <HEIGHT>",
+        "-n, --samples <SAMPLES>",
+        "-d, --depth <DEPTH>",
+        "-g, --gamma <GAMMA>",
+        "-i, --integrator <INTEGRATOR>",
+        "--distance <DISTANCE>",
+        "-f, --format <FORMAT>",
+        "--bits <BITS>",
+        "--seed <SEED>",
+        "--pass <SAMPLES>",
+        "--noise <THRESHOLD>",
+        "--sample-map <FILE>",
+        "--checkpoint <FILE>",
+        "--resume",
+        "--region <X,Y,WIDTH,HEIGHT>",
+        "--tile-order <TILE_ORDER>",
+        "--listen <ADDRESS>",
+        "--exposure <EV>",
+        "--tone-map <TONE_MAP>",
+        "-j, --threads <THREADS>",
+        "-q, --quiet",
+    ] {
+        assert!(help.contains(flag), "missing {:?} in:\n{}", flag, help);
+    }
+    for value in [
+        "- random:",
+        "- cornell:",
+        "- path:",
+        "- normals:",
+        "- bounces:",
+        "- ppm:",
+        "- webp:",
+        "- png:",
+        "- exr:",
+        "- hdr:",
+    ] {
+        assert!(help.contains(value), "missing {:?} in:\n{}", value, help);
+    }
+}
+
+#[test]
+fn short_help_is_a_summary() {
+    let output = rez(&["render", "-h"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("  -W, --width <WIDTH>  "));
+}
+
+#[test]
+fn unknown_extension_is_refused() {
+    let output = rez(&["render", "image.xyz"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        stderr(&output),
+        "rez: can't tell the image format from `image.xyz`, use --format to choose one\n"
+    );
+}
+
+#[test]
+fn unsupported_bit_depth_is_refused() {
+    let output = rez(&["render", "--bits", "16", "image.webp"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        stderr(&output),
+        "rez: webp images can't have 16 bits per channel\n"
+    );
+}
+
+#[test]
+fn passes_need_a_file() {
+    let output = rez(&["render", "--pass", "4", "-f", "ppm", "-"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        stderr(&output),
+        "rez: --pass needs an output file to rewrite\n"
+    );
+}
+
+#[test]
+fn min_samples_needs_noise() {
+    let output = rez(&["render", "--min-samples", "4", "a.ppm"]);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("--noise <THRESHOLD>"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn resume_needs_a_checkpoint() {
+    let output = rez(&["render", "--resume", "a.ppm"]);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("--checkpoint <FILE>"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn region_must_fit_the_image() {
+    let output = rez(&[
+        "render",
+        "-W",
+        "30",
+        "-H",
+        "20",
+        "--region",
+        "25,0,10,10",
+        "a.ppm",
+    ]);
+    assert!(!output.status.success());
+    assert_eq!(
+        stderr(&output),
+        "rez: --region must be inside the 30x20 image\n"
+    );
+}
+
+#[test]
+fn width_and_height_must_keep_the_aspect_ratio() {
+    let output = rez(&["render", "-W", "30", "-H", "30", "a.ppm"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        stderr(&output),
+        "rez: a 30x30 image doesn't match the scene's aspect ratio of 1.500, \
+         give only --width or --height\n"
+    );
+}
+
+#[test]
+fn samples_and_gamma_are_checked() {
+    for (args, message) in [
+        (&["-n", "0"][..], "rez: --samples must be at least 1\n"),
+        (&["-g", "0"][..], "rez: --gamma must be positive\n"),
+        (&["-g", "NaN"][..], "rez: --gamma must be positive\n"),
+        (&["--gamma=-2.2"][..], "rez: --gamma must be positive\n"),
+    ] {
+        let output = rez(&[&["render"], args, &["a.ppm"]].concat());
+        assert!(!output.status.success());
+        assert_eq!(stderr(&output), message);
+    }
+}
+
+#[test]
+fn listening_needs_a_seed_for_the_random_scene() {
+    let output = rez(&["render", "--listen", "127.0.0.1:0", "a.ppm"]);
+    assert!(!output.status.success());
+    assert_eq!(
+        stderr(&output),
+        "rez: --listen needs a --seed, so that workers lay out the random scene the same\n"
+    );
+}
+
+#[test]
+fn scene_and_builtin_conflict() {
+    let output = rez(&["render", "-s", "a.toml", "-b", "cornell", "a.ppm"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("cannot be used with"));
+}
+
+#[test]
+fn check_scene_file() {
+    let output = rez(&["check", "scenes/cornell.toml"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stderr(&output), "scenes/cornell.toml: ok\n");
+}
+
+#[test]
+fn render_small_image() {
+    let path = env::temp_dir().join(format!("rez-cli-{}.ppm", std::process::id()));
+    let output = rez(&[
+        "render",
+        "-W",
+        "6",
+        "-n",
+        "1",
+        "--seed",
+        "1",
+        "-q",
+        path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let image = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(image.starts_with("P3\n6 4\n255\n"), "{}", image);
+    assert_eq!(image.lines().count(), 3 + 6 * 4);
+}
+
+#[test]
+fn render_cornell_box() {
+    let path = env::temp_dir().join(format!("rez-cli-cornell-{}.ppm", std::process::id()));
+    let output = rez(&[
+        "render",
+        "-b",
+        "cornell",
+        "-W",
+        "24",
+        "-n",
+        "16",
+        "--seed",
+        "1",
+        "-q",
+        path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let image = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(image.starts_with("P3\n24 24\n255\n"), "{}", image);
+    let pixels: Vec<Vec<u32>> = image
+        .lines()
+        .skip(3)
+        .map(|l| l.split(' ').map(|v| v.parse().unwrap()).collect())
+        .collect();
+    assert_eq!(pixels.len(), 24 * 24);
+
+    // The ceiling light is seen directly, and lights a green wall on the left and a red one on
+    // the right.
+    assert!(pixels.iter().any(|p| p == &[255, 255, 255]));
+    let wall = |x: usize| {
+        (6..18).fold([0, 0], |[r, g], y| {
+            let p = &pixels[y * 24 + x];
+            [r + p[0], g + p[1]]
+        })
+    };
+    let (left, right) = (wall(1), wall(22));
+    assert!(2 * left[1] > 3 * left[0], "left wall is {:?}", left);
+    assert!(2 * right[0] > 3 * right[1], "right wall is {:?}", right);
+}
+
+#[test]
+fn render_on_workers() {
+    let dir = env::temp_dir();
+    let local = dir.join(format!("rez-cli-local-{}.ppm", std::process::id()));
+    let shared = dir.join(format!("rez-cli-shared-{}.ppm", std::process::id()));
+    let args = ["-W", "45", "-n", "8", "--seed", "2", "-q"];
+
+    let output = rez(&[&["render"], &args[..], &[local.to_str().unwrap()]].concat());
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let mut coordinator = Command::new(env!("CARGO_BIN_EXE_rez"))
+        .args(["render", "--listen", "127.0.0.1:0", "--job-size", "8"])
+        .args(args)
+        .arg(&shared)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(coordinator.stderr.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let address = line.trim().strip_prefix("rez: listening on ").unwrap();
+
+    // A worker which takes a tile and then hangs up without rendering it.
+    {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut magic_and_fingerprint = [0; 16];
+        stream.read_exact(&mut magic_and_fingerprint).unwrap();
+        for _ in 0..read_u32(&mut stream) {
+            let mut arg = vec![0; read_u32(&mut stream) as usize];
+            stream.read_exact(&mut arg).unwrap();
+        }
+        stream.write_all(&[1]).unwrap();
+        let tile: Vec<u32> = (0..4).map(|_| read_u32(&mut stream)).collect();
+        assert!(tile[2] > 0 && tile[3] > 0, "{:?}", tile);
+    }
+
+    // The coordinator only finishes once every tile is in, so this worker must render the
+    // abandoned one too.
+    let worker = Command::new(env!("CARGO_BIN_EXE_rez"))
+        .args(["worker", address, "-j", "1"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .status()
+        .unwrap();
+    assert!(worker.success());
+    assert!(coordinator.wait().unwrap().success());
+    let (expected, actual) = (fs::read(&local).unwrap(), fs::read(&shared).unwrap());
+    fs::remove_file(&local).unwrap();
+    fs::remove_file(&shared).unwrap();
+    assert!(expected == actual, "images differ");
+}
@@ -0,0 +1,73 @@
+//! Fixtures and checks shared between the integration tests. Each test uses only some of them.
+#![allow(dead_code)]
+
+use std::{fmt::Debug, sync::Arc};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use rez::{random_scene, Bvh, Camera, Colour, Raytracer, Vec3};
+
+/// Tolerance for values which should only differ by rounding.
+pub const EPSILON: f64 = 1e-9;
+
+/// The random built-in scene laid out by `layout`, seen from the usual viewpoint with a little
+/// depth of field, and bouncing up to 8 times. The layout is also its scene id.
+pub fn random_raytracer(layout: u64, width: u32, height: u32, samples: u32) -> Raytracer {
+    let cam = Camera::builder()
+        .origin(Vec3::new(13.0, 2.0, 3.0))
+        .target(Vec3::new(0.0, 0.0, 0.0))
+        .vup(Vec3::new(0.0, 1.0, 0.0))
+        .v_fov(f64::to_radians(20.0))
+        .aspect_ratio(1.5)
+        .aperture(0.1)
+        .focus_dist(10.0)
+        .build()
+        .unwrap();
+    let scene = Bvh::new(random_scene(&mut StdRng::seed_from_u64(layout)));
+
+    let mut r = Raytracer::new(Arc::new(scene), cam, width, height, samples, 8);
+    r.scene_id = Some(layout);
+    r
+}
+
+/// Values which can be told apart by how far they are from each other.
+pub trait Distance: Copy {
+    fn distance(self, other: Self) -> f64;
+}
+
+impl Distance for f64 {
+    fn distance(self, other: f64) -> f64 {
+        (self - other).abs()
+    }
+}
+
+impl Distance for (f64, f64) {
+    fn distance(self, other: (f64, f64)) -> f64 {
+        (self.0 - other.0).abs() + (self.1 - other.1).abs()
+    }
+}
+
+impl Distance for Vec3 {
+    fn distance(self, other: Vec3) -> f64 {
+        (self - other).length()
+    }
+}
+
+impl Distance for Colour {
+    fn distance(self, other: Colour) -> f64 {
+        (self.r - other.r).abs() + (self.g - other.g).abs() + (self.b - other.b).abs()
+    }
+}
+
+pub fn close<T: Distance>(a: T, b: T, tolerance: f64) -> bool {
+    a.distance(b) < tolerance
+}
+
+pub fn assert_close<T: Distance + Debug>(actual: T, expected: T, tolerance: f64) {
+    assert!(
+        close(actual, expected, tolerance),
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    ops::ControlFlow,
+    thread,
+};
+
+use rez::{coordinate, work, DistributedError, Raytracer};
+
+use common::random_raytracer;
+
+fn raytracer(layout: u64) -> Raytracer {
+    random_raytracer(layout, 30, 20, 4)
+}
+
+fn setup() -> Vec<String> {
+    vec!["layout".to_string(), "3".to_string()]
+}
+
+/// A worker building its raytracer from the setup the coordinator sends.
+fn worker(address: std::net::SocketAddr) -> thread::JoinHandle<Result<usize, DistributedError>> {
+    thread::spawn(move || {
+        let stream = TcpStream::connect(address).unwrap();
+        work(stream, |setup| {
+            assert_eq!(setup, self::setup());
+            Ok(raytracer(setup[1].parse()?))
+        })
+    })
+}
+
+#[test]
+fn workers_render_the_same_image() {
+    let r = raytracer(3);
+    let expected = r.render_progressive(4, |_| ControlFlow::Continue(()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let workers = [worker(address), worker(address)];
+    let image = coordinate(&r, listener, &setup(), 8).unwrap();
+    assert_eq!(image, expected);
+
+    // 4x3 tiles between them.
+    let tiles: usize = workers.map(|w| w.join().unwrap().unwrap()).iter().sum();
+    assert_eq!(tiles, 12);
+}
+
+#[test]
+fn tiles_of_lost_workers_are_reassigned() {
+    let r = raytracer(3);
+    let expected = r.render_progressive(4, |_| ControlFlow::Continue(()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let coordinator = thread::spawn(move || coordinate(&raytracer(3), listener, &setup(), 8));
+
+    // Take a tile and vanish without rendering it.
+    {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut handshake = [0; 8 + 8 + 4 + (4 + 6) + (4 + 1)];
+        stream.read_exact(&mut handshake).unwrap();
+        stream.write_all(&[1]).unwrap();
+        let mut job = [0; 16];
+        stream.read_exact(&mut job).unwrap();
+    }
+
+    let tiles = worker(address).join().unwrap().unwrap();
+    assert_eq!(tiles, 12);
+    assert_eq!(coordinator.join().unwrap().unwrap(), expected);
+}
+
+#[test]
+fn workers_with_another_scene_are_turned_away() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let coordinator = thread::spawn(move || coordinate(&raytracer(3), listener, &setup(), 16));
+
+    let stream = TcpStream::connect(address).unwrap();
+    let wrong = work(stream, |_| Ok(raytracer(4)));
+    assert!(matches!(wrong, Err(DistributedError::Mismatch)));
+
+    let tiles = worker(address).join().unwrap().unwrap();
+    assert_eq!(tiles, 4);
+    coordinator.join().unwrap().unwrap();
+}
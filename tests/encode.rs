@@ -0,0 +1,203 @@
+use std::io::Cursor;
+
+use exr::prelude::{read, Compression, ReadChannels, ReadLayers};
+
+use rez::{
+    encode_exr_with, encode_hdr, encode_png, encode_png_with, BitDepth, Colour, ExrCompression,
+    ExrOptions, PngOptions, Precision,
+};
+
+const WIDTH: u32 = 5;
+const HEIGHT: u32 = 3;
+
+fn image() -> Vec<Colour> {
+    (0..WIDTH * HEIGHT)
+        .map(|i| {
+            let v = i as f64 / (WIDTH * HEIGHT - 1) as f64;
+            Colour::new(v, 1.0 - v, (v * 7.0).fract())
+        })
+        .collect()
+}
+
+fn decode(bytes: &[u8]) -> (png::OutputInfo, Vec<u8>) {
+    let mut reader = png::Decoder::new(bytes).read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    buf.truncate(info.buffer_size());
+    (info, buf)
+}
+
+#[test]
+fn png_8bit_round_trip() {
+    let data = image();
+    let mut bytes = Vec::new();
+    encode_png(&data, WIDTH, HEIGHT, &mut bytes).unwrap();
+
+    let (info, buf) = decode(&bytes);
+    assert_eq!((info.width, info.height), (WIDTH, HEIGHT));
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+    assert_eq!(info.bit_depth, png::BitDepth::Eight);
+
+    let expected: Vec<u8> = data
+        .iter()
+        .map(Colour::to_24bit_rgb)
+        .flat_map(|(r, g, b)| [r, g, b])
+        .collect();
+    assert_eq!(buf, expected);
+}
+
+#[test]
+fn png_16bit_round_trip() {
+    let data = image();
+    let mut bytes = Vec::new();
+    let options = PngOptions {
+        bit_depth: BitDepth::Sixteen,
+        alpha: false,
+    };
+    encode_png_with(&data, WIDTH, HEIGHT, &mut bytes, options).unwrap();
+
+    let (info, buf) = decode(&bytes);
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+    assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+
+    let decoded: Vec<u16> = buf
+        .chunks_exact(2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .collect();
+    for (c, px) in data.iter().zip(decoded.chunks_exact(3)) {
+        for (v, &d) in [c.r, c.g, c.b].iter().zip(px) {
+            assert!((v - d as f64 / 65535.0).abs() <= 0.5 / 65535.0);
+        }
+    }
+}
+
+#[test]
+fn png_alpha_is_opaque() {
+    let data = image();
+    for bit_depth in [BitDepth::Eight, BitDepth::Sixteen] {
+        let mut bytes = Vec::new();
+        let options = PngOptions {
+            bit_depth,
+            alpha: true,
+        };
+        encode_png_with(&data, WIDTH, HEIGHT, &mut bytes, options).unwrap();
+
+        let (info, buf) = decode(&bytes);
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        let sample = buf.len() / (WIDTH * HEIGHT * 4) as usize;
+        for px in buf.chunks_exact(4 * sample) {
+            assert!(px[3 * sample..].iter().all(|&b| b == 0xff));
+        }
+
+        let mut rgb = Vec::new();
+        encode_png_with(
+            &data,
+            WIDTH,
+            HEIGHT,
+            &mut rgb,
+            PngOptions {
+                bit_depth,
+                alpha: false,
+            },
+        )
+        .unwrap();
+        let (_, rgb) = decode(&rgb);
+        let stripped: Vec<u8> = buf
+            .chunks_exact(4 * sample)
+            .flat_map(|px| px[..3 * sample].to_vec())
+            .collect();
+        assert_eq!(stripped, rgb);
+    }
+}
+
+/// Linear radiance, including values well above 1.
+fn radiance() -> Vec<Colour> {
+    (0..WIDTH * HEIGHT)
+        .map(|i| {
+            let v = i as f64 / 3.0;
+            Colour::new(v, 0.01 * v, 1000.0 / (1.0 + v))
+        })
+        .collect()
+}
+
+fn decode_exr(bytes: &[u8]) -> (Compression, Vec<[f32; 3]>) {
+    let image = read()
+        .no_deep_data()
+        .largest_resolution_level()
+        .rgb_channels(
+            |size, _| vec![[0.0; 3]; size.width() * size.height()],
+            |pixels: &mut Vec<[f32; 3]>, p, (r, g, b): (f32, f32, f32)| {
+                pixels[p.y() * WIDTH as usize + p.x()] = [r, g, b]
+            },
+        )
+        .first_valid_layer()
+        .all_attributes()
+        .from_buffered(Cursor::new(bytes))
+        .unwrap();
+
+    let layer = image.layer_data;
+    assert_eq!(layer.size.width(), WIDTH as usize);
+    assert_eq!(layer.size.height(), HEIGHT as usize);
+    (layer.encoding.compression, layer.channel_data.pixels)
+}
+
+#[test]
+fn exr_round_trip() {
+    let data = radiance();
+    for (precision, tolerance) in [(Precision::Half, 1e-3), (Precision::Full, 1e-7)] {
+        for (compression, expected) in [
+            (ExrCompression::None, Compression::Uncompressed),
+            (ExrCompression::Zip, Compression::ZIP16),
+        ] {
+            let mut bytes = Vec::new();
+            let options = ExrOptions {
+                precision,
+                compression,
+            };
+            encode_exr_with(&data, WIDTH, HEIGHT, &mut bytes, options).unwrap();
+
+            let (actual, pixels) = decode_exr(&bytes);
+            assert_eq!(actual, expected);
+            for (c, px) in data.iter().zip(pixels) {
+                for (&v, d) in [c.r, c.g, c.b].iter().zip(px) {
+                    let error = (v - d as f64).abs() / v.max(1e-3);
+                    assert!(
+                        error <= tolerance,
+                        "{:?} {:?}: {} decoded as {}",
+                        precision,
+                        compression,
+                        v,
+                        d
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn hdr_round_trip() {
+    let data = radiance();
+    let mut bytes = Vec::new();
+    encode_hdr(&data, WIDTH, HEIGHT, &mut bytes).unwrap();
+    assert!(bytes.starts_with(b"#?RADIANCE\n"));
+
+    let image = image::load_from_memory_with_format(&bytes, image::ImageFormat::Hdr)
+        .unwrap()
+        .into_rgb32f();
+    assert_eq!(image.dimensions(), (WIDTH, HEIGHT));
+
+    // RGBE shares one exponent between channels, so small channels lose precision relative to
+    // the largest one.
+    for (c, px) in data.iter().zip(image.pixels()) {
+        let max = c.r.max(c.g).max(c.b);
+        for (&v, &d) in [c.r, c.g, c.b].iter().zip(px.0.iter()) {
+            assert!(
+                (v - d as f64).abs() <= max / 128.0,
+                "{} decoded as {}",
+                v,
+                d
+            );
+        }
+    }
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use std::sync::Arc;
+
+use rez::{
+    Albedo, AmbientOcclusion, BounceHeatmap, Camera, Colour, Depth, DiffuseLight, Integrator,
+    Lambertian, Material, Normals, PathTracer, Raytracer, Scene, Sphere, Vec3,
+};
+
+use common::assert_close;
+
+/// A very narrow view of a lone sphere, straight ahead of the camera at a distance of 9 to its
+/// surface, against a black sky.
+fn raytracer(integrator: impl Integrator + Send + Sync + 'static) -> Raytracer {
+    looking_at(Lambertian::new(Colour::new(0.2, 0.4, 0.6)), integrator)
+}
+
+fn looking_at(
+    material: impl Material + Send + Sync + 'static,
+    integrator: impl Integrator + Send + Sync + 'static,
+) -> Raytracer {
+    let cam = Camera::builder()
+        .origin(Vec3::new(0.0, 0.0, 10.0))
+        .target(Vec3::ZERO)
+        .vup(Vec3::new(0.0, 1.0, 0.0))
+        .v_fov(f64::to_radians(0.01))
+        .aspect_ratio(1.0)
+        .aperture(0.0)
+        .focus_dist(10.0)
+        .build()
+        .unwrap();
+    let scene: Scene = vec![Box::new(Sphere::new(Vec3::ZERO, 1.0, Arc::new(material)))];
+
+    let mut r = Raytracer::new(Arc::new(scene), cam, 3, 3, 4, 10);
+    r.integrator = Arc::new(integrator);
+    r.background = Arc::new(Colour::BLACK);
+    r
+}
+
+#[test]
+fn normals_face_the_camera() {
+    for pixel in raytracer(Normals).render_linear() {
+        assert_close(pixel, Colour::new(0.5, 0.5, 1.0), 1e-2);
+    }
+}
+
+#[test]
+fn depth_is_a_fraction_of_far() {
+    for pixel in raytracer(Depth::new(18.0)).render_linear() {
+        assert_close(pixel, Colour::new(0.5, 0.5, 0.5), 1e-2);
+    }
+}
+
+#[test]
+fn albedo_is_the_surface_colour() {
+    for pixel in raytracer(Albedo).render_linear() {
+        assert_close(pixel, Colour::new(0.2, 0.4, 0.6), 1e-2);
+    }
+}
+
+#[test]
+fn nothing_occludes_a_lone_sphere() {
+    for pixel in raytracer(AmbientOcclusion::new(100.0)).render_linear() {
+        assert_close(pixel, Colour::WHITE, 1e-2);
+    }
+}
+
+#[test]
+fn paths_off_a_lone_sphere_bounce_once() {
+    // One bounce out of a possible ten is a fifth of the way from blue to green.
+    for pixel in raytracer(BounceHeatmap).render_linear() {
+        assert_close(pixel, Colour::new(0.0, 0.2, 0.8), 1e-2);
+    }
+}
+
+#[test]
+fn lights_are_seen_by_their_emission() {
+    let emit = Colour::new(4.0, 2.0, 1.0);
+    for pixel in looking_at(DiffuseLight::new(emit), PathTracer::default()).render_linear() {
+        assert_close(pixel, emit, 1e-2);
+    }
+}
@@ -0,0 +1,118 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use rez::{
+    Collider, Colour, Dielectric, HenyeyGreenstein, Isotropic, Lambertian, Material, Metal, Ray,
+    Sphere, Vec3,
+};
+
+/// A ray which isn't a unit vector, hitting a unit sphere at an angle.
+fn ray() -> Ray {
+    Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.1, 0.05, -1.0) * 2.0)
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0)
+}
+
+/// Check that what `sample` reports agrees with `eval` and `pdf`, and that `pdf` integrates to
+/// one over the sphere of directions.
+fn check_consistent<M: Material>(material: M) {
+    let sphere = Sphere::new(Vec3::ZERO, 1.0, Arc::new(material));
+    let ray = ray();
+    let col = sphere.collide(ray, (0.001, f64::INFINITY)).unwrap();
+    let (m, wo) = (col.material, -ray.dir);
+    assert!(!m.is_specular());
+
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..1000 {
+        let s = match m.sample(&col, wo, &mut rng) {
+            Some(s) => s,
+            None => continue,
+        };
+        let pdf = m.pdf(&col, wo, s.dir);
+        assert!(close(s.pdf, pdf), "sampled with {} but pdf {}", s.pdf, pdf);
+        let f = m.eval(&col, wo, s.dir);
+        for (w, f) in [(s.weight.r, f.r), (s.weight.g, f.g), (s.weight.b, f.b)] {
+            assert!(close(w, f / pdf), "weight {} but eval / pdf {}", w, f / pdf);
+        }
+    }
+
+    const N: usize = 400_000;
+    let total: f64 = (0..N)
+        .map(|_| m.pdf(&col, wo, Vec3::random_unit(&mut rng)))
+        .sum();
+    let integral = total / N as f64 * 4.0 * PI;
+    assert!(
+        (integral - 1.0).abs() < 0.03,
+        "pdf integrates to {}",
+        integral
+    );
+}
+
+#[test]
+fn lambertian_is_consistent() {
+    check_consistent(Lambertian::new(Colour::new(0.2, 0.5, 0.8)));
+}
+
+#[test]
+fn fuzzy_metal_is_consistent() {
+    check_consistent(Metal::new(Colour::new(0.9, 0.6, 0.3), 0.5));
+    check_consistent(Metal::new(Colour::new(0.9, 0.6, 0.3), 1.0));
+}
+
+#[test]
+fn phase_functions_are_consistent() {
+    check_consistent(Isotropic::new(Colour::new(0.5, 0.5, 0.5)));
+    check_consistent(HenyeyGreenstein::new(Colour::WHITE, 0.6));
+    check_consistent(HenyeyGreenstein::new(Colour::WHITE, -0.3));
+}
+
+#[test]
+fn henyey_greenstein_mean_cosine_is_g() {
+    let sphere = Sphere::new(Vec3::ZERO, 1.0, Arc::new(Lambertian::new(Colour::WHITE)));
+    let ray = ray();
+    let col = sphere.collide(ray, (0.001, f64::INFINITY)).unwrap();
+    let forward = ray.dir.unit();
+
+    let mut rng = StdRng::seed_from_u64(7);
+    for g in [-0.7, 0.0, 0.4, 0.9] {
+        let phase = HenyeyGreenstein::new(Colour::WHITE, g);
+        const N: usize = 100_000;
+        let total: f64 = (0..N)
+            .map(|_| {
+                let s = phase.sample(&col, -ray.dir, &mut rng).unwrap();
+                s.dir.unit().dot(forward)
+            })
+            .sum();
+        let mean = total / N as f64;
+        assert!(
+            (mean - g).abs() < 0.01,
+            "g = {} but mean cosine {}",
+            g,
+            mean
+        );
+    }
+}
+
+#[test]
+fn specular_materials_have_no_density() {
+    let ray = ray();
+    let materials: [Arc<dyn Material + Send + Sync>; 2] = [
+        Arc::new(Metal::new(Colour::WHITE, 0.0)),
+        Arc::new(Dielectric::new(1.5)),
+    ];
+    let mut rng = StdRng::seed_from_u64(7);
+    for material in materials {
+        let sphere = Sphere::new(Vec3::ZERO, 1.0, Arc::new(material));
+        let col = sphere.collide(ray, (0.001, f64::INFINITY)).unwrap();
+        let m = col.material;
+        assert!(m.is_specular());
+
+        let s = m.sample(&col, -ray.dir, &mut rng).unwrap();
+        assert!(s.pdf > 0.0 && s.pdf <= 1.0);
+        assert_eq!(m.eval(&col, -ray.dir, s.dir), Colour::BLACK);
+        assert_eq!(m.pdf(&col, -ray.dir, s.dir), 0.0);
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use rez::{
+    Atmosphere, Camera, Collider, Colour, ConstantMedium, DiffuseLight, Isotropic, Lambertian, Ray,
+    Raytracer, Scene, Sphere, Vec3,
+};
+
+/// Check that a fraction `passed` of `n` rays getting through agrees with `expected` to within
+/// four standard errors.
+fn check_transmittance(passed: usize, n: usize, expected: f64) {
+    let transmittance = passed as f64 / n as f64;
+    let error = (expected * (1.0 - expected) / n as f64).sqrt();
+    assert!(
+        (transmittance - expected).abs() < 4.0 * error,
+        "{} of rays got through, expected {}",
+        transmittance,
+        expected
+    );
+}
+
+#[test]
+fn constant_medium_follows_beer_lambert() {
+    let boundary = Sphere::new(Vec3::ZERO, 1.0, Arc::new(Lambertian::new(Colour::WHITE)));
+    let density = 0.5;
+    let medium = ConstantMedium::new(boundary, density, Isotropic::new(Colour::WHITE));
+
+    const N: usize = 100_000;
+    let mut rng = StdRng::seed_from_u64(1);
+    // Straight through the middle, so travelling 2 units inside.
+    let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -3.0));
+    let passed = (0..N)
+        .filter(|_| {
+            let ray = ray.with_random(rng.gen());
+            medium.collide(ray, (0.001, f64::INFINITY)).is_none()
+        })
+        .count();
+
+    check_transmittance(passed, N, (-density * 2.0).exp());
+}
+
+#[test]
+fn narrowing_the_range_keeps_the_scattering_point() {
+    let boundary = Sphere::new(Vec3::ZERO, 1.0, Arc::new(Lambertian::new(Colour::WHITE)));
+    let medium = ConstantMedium::new(boundary, 2.0, Isotropic::new(Colour::WHITE));
+
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut scattered = 0;
+    for _ in 0..1000 {
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let ray = ray.with_random(rng.gen());
+        let t = match medium.collide(ray, (0.001, f64::INFINITY)) {
+            Some(c) => c.t,
+            None => continue,
+        };
+        scattered += 1;
+        let narrowed = medium.collide(ray, (0.001, t + 1e-6)).map(|c| c.t);
+        assert_eq!(narrowed, Some(t));
+        assert!(medium.collide(ray, (0.001, t - 1e-6)).is_none());
+    }
+    assert!(scattered > 500, "only {} rays scattered", scattered);
+}
+
+#[test]
+fn overlapping_media_scatter_independently() {
+    let boundary = || Sphere::new(Vec3::ZERO, 1.0, Arc::new(Lambertian::new(Colour::WHITE)));
+    let (thin, thick) = (0.2, 0.5);
+    let scene: Scene = vec![
+        Box::new(ConstantMedium::new(
+            boundary(),
+            thin,
+            Isotropic::new(Colour::WHITE),
+        )),
+        Box::new(ConstantMedium::new(
+            boundary(),
+            thick,
+            Isotropic::new(Colour::WHITE),
+        )),
+    ];
+
+    const N: usize = 100_000;
+    let mut rng = StdRng::seed_from_u64(2);
+    let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    let passed = (0..N)
+        .filter(|_| {
+            let ray = ray.with_random(rng.gen());
+            scene.collide(ray, (0.001, f64::INFINITY)).is_none()
+        })
+        .count();
+
+    // Together they are as dense as both added up.
+    check_transmittance(passed, N, (-(thin + thick) * 2.0).exp());
+}
+
+#[test]
+fn constant_medium_scatters_only_inside_its_range() {
+    let boundary = Sphere::new(Vec3::ZERO, 1.0, Arc::new(Lambertian::new(Colour::WHITE)));
+    let medium = ConstantMedium::new(boundary, 1e6, Isotropic::new(Colour::WHITE));
+    let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+
+    let hit = medium.collide(ray, (0.001, f64::INFINITY)).unwrap();
+    assert!((hit.t - 4.0).abs() < 1e-3, "scattered at {}", hit.t);
+    assert!(medium.collide(ray, (0.001, 3.9)).is_none());
+    assert!(medium.collide(ray, (6.1, f64::INFINITY)).is_none());
+}
+
+#[test]
+fn atmosphere_dims_lights_by_beer_lambert() {
+    let cam = Camera::builder()
+        .origin(Vec3::ZERO)
+        .target(Vec3::new(0.0, 0.0, -1.0))
+        .vup(Vec3::new(0.0, 1.0, 0.0))
+        .v_fov(f64::to_radians(0.01))
+        .aspect_ratio(1.0)
+        .aperture(0.0)
+        .focus_dist(1.0)
+        .build()
+        .unwrap();
+    // A glowing wall 4 units away, so big that it's flat where the camera sees it.
+    let scene: Scene = vec![Box::new(Sphere::new(
+        Vec3::new(0.0, 0.0, -1004.0),
+        1000.0,
+        Arc::new(DiffuseLight::new(Colour::WHITE)),
+    ))];
+
+    let density = 0.25;
+    let samples = 5000;
+    let mut r = Raytracer::new(Arc::new(scene), cam, 2, 2, samples, 4);
+    // Absorbing haze, so that only light coming straight from the wall gets through.
+    r.atmosphere = Some(Atmosphere::new(density, Isotropic::new(Colour::BLACK)));
+    r.background = Arc::new(Colour::BLACK);
+
+    let image = r.render_linear();
+    let mean = image.iter().map(|c| c.r).sum::<f64>() / image.len() as f64;
+    let n = image.len() * samples as usize;
+    check_transmittance(
+        (mean * n as f64).round() as usize,
+        n,
+        (-density * 4.0).exp(),
+    );
+}
+
+#[test]
+fn atmosphere_extent_reveals_the_background() {
+    let mut atmosphere = Atmosphere::new(1e6, Isotropic::new(Colour::WHITE));
+    let ray = Ray::new(Vec3::ZERO, Vec3::new(0.0, 0.0, -2.0));
+    assert!(atmosphere.collide(ray, (0.001, f64::INFINITY)).is_some());
+
+    atmosphere.extent = 0.001;
+    assert!(atmosphere.collide(ray, (0.001, f64::INFINITY)).is_none());
+}
@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use rez::{Collider, Colour, Lambertian, MeshError, Ray, Scene, Triangle, TriangleMesh, Vec3};
+
+fn grey() -> Arc<Lambertian> {
+    Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)))
+}
+
+fn random_point(rng: &mut StdRng, size: f64) -> Vec3 {
+    Vec3::new(
+        rng.gen_range(-size..size),
+        rng.gen_range(-size..size),
+        rng.gen_range(-size..size),
+    )
+}
+
+#[test]
+fn mesh_matches_brute_force() {
+    let mut rng = StdRng::seed_from_u64(0x3e54);
+    let positions: Vec<Vec3> = (0..200).map(|_| random_point(&mut rng, 10.0)).collect();
+    // Small triangles between nearby-numbered vertices, so some share edges.
+    let triangles: Vec<[usize; 3]> = (0..300)
+        .map(|_| {
+            let a = rng.gen_range(0..positions.len() - 2);
+            let b = a + 1;
+            let c = a + 2;
+            [a, b, c]
+        })
+        .collect();
+
+    let flat: Scene = triangles
+        .iter()
+        .map(|&[a, b, c]| -> Box<dyn Collider + Send + Sync> {
+            Box::new(Triangle::new(
+                [positions[a], positions[b], positions[c]],
+                grey(),
+            ))
+        })
+        .collect();
+    let mesh = TriangleMesh::new(positions, triangles, grey()).unwrap();
+    assert_eq!(mesh.bounding_box(), flat.bounding_box());
+
+    let mut hits = 0;
+    for _ in 0..20_000 {
+        let target = random_point(&mut rng, 10.0);
+        let orig = random_point(&mut rng, 20.0);
+        let ray = Ray::new(orig, target - orig);
+
+        let expected = flat.collide(ray, (0.001, f64::INFINITY));
+        let actual = mesh.collide(ray, (0.001, f64::INFINITY));
+        match (expected, actual) {
+            (None, None) => {}
+            (Some(e), Some(a)) => {
+                hits += 1;
+                assert_eq!(e.t, a.t);
+                assert_eq!(e.point, a.point);
+                assert_eq!(e.normal, a.normal);
+                assert_eq!(e.front, a.front);
+                assert_eq!(e.barycentric, a.barycentric);
+            }
+            (e, a) => panic!(
+                "brute force hit {:?} but the mesh hit {:?}",
+                e.map(|c| c.t),
+                a.map(|c| c.t)
+            ),
+        }
+    }
+    assert!(hits > 1000, "too few rays hit anything: {}", hits);
+}
+
+#[test]
+fn smooth_normals_are_interpolated() {
+    let positions = vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    ];
+    let normals = vec![
+        Vec3::new(0.0, 0.0, 1.0),
+        Vec3::new(1.0, 0.0, 1.0).unit(),
+        Vec3::new(0.0, 1.0, 1.0).unit(),
+    ];
+    let mesh =
+        TriangleMesh::with_normals(positions.clone(), normals.clone(), vec![[0, 1, 2]], grey())
+            .unwrap();
+
+    for weights in [
+        [1.0, 0.0, 0.0],
+        [0.2, 0.3, 0.5],
+        [0.6, 0.4, 0.0],
+        [1.0 / 3.0; 3],
+    ] {
+        let point =
+            positions[0] * weights[0] + positions[1] * weights[1] + positions[2] * weights[2];
+        let expected =
+            (normals[0] * weights[0] + normals[1] * weights[1] + normals[2] * weights[2]).unit();
+
+        let above = Ray::new(point + Vec3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = mesh.collide(above, (0.001, f64::INFINITY)).unwrap();
+        assert!(hit.front);
+        assert!(
+            (hit.normal - expected).length() < 1e-9,
+            "{} at {:?}",
+            hit.normal,
+            weights
+        );
+        let found = hit.barycentric.unwrap();
+        for (f, w) in found.iter().zip(weights) {
+            assert!((f - w).abs() < 1e-9, "{:?} for {:?}", found, weights);
+        }
+
+        // From behind, the shading normal is flipped to face the ray like the geometric one.
+        let below = Ray::new(point - Vec3::new(0.0, 0.0, 2.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit = mesh.collide(below, (0.001, f64::INFINITY)).unwrap();
+        assert!(!hit.front);
+        assert!(
+            (hit.normal + expected).length() < 1e-9,
+            "{} at {:?}",
+            hit.normal,
+            weights
+        );
+    }
+}
+
+#[test]
+fn flat_meshes_use_the_geometric_normal() {
+    let mesh = TriangleMesh::new(
+        vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        ],
+        vec![[0, 1, 2]],
+        grey(),
+    )
+    .unwrap();
+    let ray = Ray::new(Vec3::new(0.2, 3.0, -0.2), Vec3::new(0.0, -1.0, 0.0));
+    let hit = mesh.collide(ray, (0.001, f64::INFINITY)).unwrap();
+    assert!(hit.front);
+    assert_eq!(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+}
+
+fn error<T>(result: Result<T, MeshError>) -> String {
+    match result {
+        Err(e) => e.to_string(),
+        Ok(_) => panic!("built a bad mesh"),
+    }
+}
+
+#[test]
+fn bad_meshes_are_refused() {
+    let positions = vec![
+        Vec3::ZERO,
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    ];
+
+    assert_eq!(
+        error(TriangleMesh::new(
+            positions.clone(),
+            vec![[0, 1, 3]],
+            grey()
+        )),
+        "mesh triangle refers to vertex 3 but there are only 3"
+    );
+
+    let normals = vec![Vec3::new(0.0, 0.0, 1.0); 2];
+    assert_eq!(
+        error(TriangleMesh::with_normals(
+            positions.clone(),
+            normals,
+            vec![[0, 1, 2]],
+            grey()
+        )),
+        "mesh has 3 vertices but 2 normals, it needs one for each"
+    );
+
+    let mesh = TriangleMesh::new(positions, vec![[0, 1, 2]], grey()).unwrap();
+    assert_eq!(
+        error(mesh.textured(vec![(0.0, 0.0); 4])),
+        "mesh has 3 vertices but 4 texture coordinates, it needs one for each"
+    );
+}
@@ -0,0 +1,142 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use rez::{
+    AnimatedInstance, Camera, CameraBuilder, Collider, Colour, DiffuseLight, Instance, Lambertian,
+    MovingSphere, Pose, Ray, Raytracer, Scene, Sphere, Vec3,
+};
+
+fn camera() -> CameraBuilder {
+    let mut builder = Camera::builder();
+    builder
+        .origin(Vec3::new(0.0, 0.0, 5.0))
+        .target(Vec3::ZERO)
+        .vup(Vec3::new(0.0, 1.0, 0.0))
+        .v_fov(f64::to_radians(0.01))
+        .aspect_ratio(1.0)
+        .aperture(0.0)
+        .focus_dist(5.0);
+    builder
+}
+
+#[test]
+fn camera_rays_are_sent_while_the_shutter_is_open() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let still = camera().build().unwrap();
+    assert_eq!(still.ray(0.5, 0.5, &mut rng).time, 0.0);
+
+    let open = camera().shutter(2.0, 3.0).build().unwrap();
+    const N: usize = 10_000;
+    let times: Vec<f64> = (0..N).map(|_| open.ray(0.5, 0.5, &mut rng).time).collect();
+    assert!(times.iter().all(|t| (2.0..3.0).contains(t)));
+    let mean = times.iter().sum::<f64>() / N as f64;
+    assert!((mean - 2.5).abs() < 0.01, "mean time {}", mean);
+
+    assert!(camera().shutter(1.0, 0.0).build().is_err());
+}
+
+#[test]
+fn scattered_rays_keep_their_time() {
+    let sphere = Sphere::new(Vec3::ZERO, 1.0, Arc::new(Lambertian::new(Colour::WHITE)));
+    let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0)).with_time(0.7);
+    let col = sphere.collide(ray, (0.001, f64::INFINITY)).unwrap();
+    let (_, scattered) = col
+        .material
+        .scatter(ray, &col, &mut StdRng::seed_from_u64(1))
+        .unwrap();
+    assert_eq!(scattered.time, 0.7);
+}
+
+#[test]
+fn moving_sphere_is_where_it_should_be_at_the_time() {
+    let material = Arc::new(Lambertian::new(Colour::WHITE));
+    let moving = MovingSphere::new(
+        (Vec3::new(-2.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0)),
+        (0.0, 1.0),
+        0.5,
+        material.clone(),
+    );
+    let down = |x: f64, time: f64| {
+        Ray::new(Vec3::new(x, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)).with_time(time)
+    };
+
+    for time in [0.0, 0.3, 0.5, 1.0] {
+        let centre = moving.centre_at(time);
+        let still = Sphere::new(centre, 0.5, material.clone());
+        let ray = down(centre.x + 0.2, time);
+        let expected = still.collide(ray, (0.001, f64::INFINITY)).unwrap();
+        let actual = moving.collide(ray, (0.001, f64::INFINITY)).unwrap();
+        assert_eq!((expected.t, expected.normal), (actual.t, actual.normal));
+        assert!(moving
+            .collide(down(centre.x + 1.0, time), (0.001, f64::INFINITY))
+            .is_none());
+    }
+
+    let b = moving.bounding_box().unwrap();
+    assert_eq!(b.min, Vec3::new(-2.5, -0.5, -0.5));
+    assert_eq!(b.max, Vec3::new(2.5, 0.5, 0.5));
+
+    // Outside its times the sphere stays at the ends of its path, inside its bounds.
+    assert_eq!(moving.centre_at(-1.0), Vec3::new(-2.0, 0.0, 0.0));
+    assert_eq!(moving.centre_at(3.0), Vec3::new(2.0, 0.0, 0.0));
+    assert!(moving
+        .collide(down(2.2, 3.0), (0.001, f64::INFINITY))
+        .is_some());
+    assert!(moving
+        .collide(down(6.0, 3.0), (0.001, f64::INFINITY))
+        .is_none());
+}
+
+#[test]
+fn keyframes_interpolate_spins_the_long_way_round() {
+    let object: Arc<dyn Collider + Send + Sync> = Arc::new(Sphere::new(
+        Vec3::new(1.0, 0.0, 0.0),
+        0.1,
+        Arc::new(Lambertian::new(Colour::WHITE)),
+    ));
+    let spin = |angle| Pose {
+        angle,
+        ..Pose::default()
+    };
+    let animated = AnimatedInstance::new(
+        object.clone(),
+        vec![(1.0, spin(2.0 * PI)), (0.0, spin(0.0))],
+    );
+
+    assert_eq!(animated.pose_at(-1.0).angle, 0.0);
+    assert_eq!(animated.pose_at(2.0).angle, 2.0 * PI);
+    assert!((animated.pose_at(0.75).angle - 1.5 * PI).abs() < 1e-12);
+
+    // Three quarters of a turn about y takes the ball from +x to +z.
+    let time = 0.75;
+    let ray = Ray::new(Vec3::new(0.0, 5.0, 1.0), Vec3::new(0.0, -1.0, 0.0)).with_time(time);
+    let posed = Instance::new(object, animated.transform_at(time));
+    let expected = posed.collide(ray, (0.001, f64::INFINITY)).unwrap();
+    let actual = animated.collide(ray, (0.001, f64::INFINITY)).unwrap();
+    assert!((expected.t - actual.t).abs() < 1e-9);
+    assert!((actual.point - Vec3::new(0.0, 0.1, 1.0)).length() < 1e-9);
+
+    // The ball sweeps out a whole ring, not just the box between its ends.
+    let b = animated.bounding_box().unwrap();
+    assert!(b.min.x <= -1.1 && b.min.z <= -1.1 && b.max.x >= 1.1 && b.max.z >= 1.1);
+}
+
+#[test]
+fn moving_lights_blur() {
+    // A glowing ball which crosses the middle of the view during the first half of the
+    // shutter interval.
+    let scene: Scene = vec![Box::new(MovingSphere::new(
+        (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(3.0, 0.0, 0.0)),
+        (0.0, 1.0),
+        1.0,
+        Arc::new(DiffuseLight::new(Colour::WHITE)),
+    ))];
+    let cam = camera().shutter(0.0, 1.0).build().unwrap();
+    let mut r = Raytracer::new(Arc::new(scene), cam, 2, 2, 4000, 2);
+    r.background = Arc::new(Colour::BLACK);
+
+    let image = r.render_linear();
+    let mean = image.iter().map(|c| c.r).sum::<f64>() / image.len() as f64;
+    assert!((mean - 0.5).abs() < 0.02, "lit for {} of the time", mean);
+}
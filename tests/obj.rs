@@ -0,0 +1,206 @@
+mod common;
+
+use std::{env, fs, path::PathBuf, process};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use rez::{load_obj, Collider, Collision, Colour, Obj, ObjError, Ray, Scene, Vec3, Warning};
+
+use common::{close, EPSILON};
+
+/// Load `obj` as `model.obj`, with `mtl` beside it as `model.mtl`, returning where the OBJ
+/// file was for checking error messages.
+fn load(name: &str, obj: &str, mtl: &str) -> (PathBuf, Result<Obj, ObjError>) {
+    let dir = env::temp_dir().join(format!("rez-obj-{}-{}", process::id(), name));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("model.obj");
+    fs::write(&path, obj).unwrap();
+    fs::write(dir.join("model.mtl"), mtl).unwrap();
+
+    let scene = load_obj(&path);
+    fs::remove_dir_all(&dir).unwrap();
+    (path, scene)
+}
+
+/// The collision of a ray straight down onto the z = 0 plane at `(x, y)`.
+fn hit(scene: &Scene, x: f64, y: f64) -> Option<Collision<'_>> {
+    let ray = Ray::new(Vec3::new(x, y, 1.0), Vec3::new(0.0, 0.0, -1.0));
+    scene.collide(ray, (0.001, f64::INFINITY))
+}
+
+#[test]
+fn face_vertex_forms() {
+    let mut obj = String::new();
+    for x in [0.0, 2.0, 4.0, 6.0] {
+        obj += &format!("v {} 0 0\nv {} 0 0\nv {} 1 0\n", x, x + 1.0, x);
+    }
+    obj += "vt 0.5 0.5\nvt 1 0.5\nvt 0.5 1\n";
+    obj += "vn 0 0.6 0.8\n";
+    obj += "g plain\nf 1 2 3\n";
+    obj += "g textured\nf 4/1 5/2 6/3\n";
+    obj += "g smooth\nf 7//1 8//1 9//1\n";
+    obj += "g both\nf 10/1/1 11/2/1 12/3/1\n";
+
+    let scene = load("forms", &obj, "").1.unwrap().scene;
+    assert_eq!(scene.len(), 4);
+
+    let flat = Vec3::new(0.0, 0.0, 1.0);
+    let tilted = Vec3::new(0.0, 0.6, 0.8);
+    // Without texture coordinates, a triangle's own barycentric coordinates stand in.
+    let (untextured, textured) = ((0.25, 0.25), (0.625, 0.625));
+    for (x, normal, uv) in [
+        (0.0, flat, untextured),
+        (2.0, flat, textured),
+        (4.0, tilted, untextured),
+        (6.0, tilted, textured),
+    ] {
+        let c = hit(&scene, x + 0.25, 0.25).unwrap();
+        assert!((c.normal - normal).length() < 1e-9, "{} at {}", c.normal, x);
+        assert!(close(c.uv, uv, EPSILON), "{:?} at {}", c.uv, x);
+    }
+}
+
+#[test]
+fn negative_indices_count_back_from_the_latest() {
+    let obj = "\
+v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 0 1
+f -3/-3 -2/-2 -1/-1
+v 2 0 0
+v 3 0 0
+v 2 1 0
+f -3/-3 -2/-2 -1/-1
+";
+    let scene = load("negative", obj, "").1.unwrap().scene;
+    for x in [0.25, 2.25] {
+        let c = hit(&scene, x, 0.25).unwrap();
+        assert!(close(c.uv, (0.25, 0.25), EPSILON), "{:?} at {}", c.uv, x);
+    }
+    assert!(hit(&scene, 1.25, 0.25).is_none());
+
+    let (_, scene) = load("too-negative", "v 0 0 0\nv 1 0 0\nf -3 -2 -1\n", "");
+    assert!(matches!(scene, Err(ObjError::Parse { line: 3, .. })));
+}
+
+#[test]
+fn polygons_are_fan_triangulated() {
+    let mut obj = String::new();
+    for i in 0..6 {
+        let (sin, cos) = (i as f64 * std::f64::consts::FRAC_PI_3).sin_cos();
+        obj += &format!("v {} {} 0\n", cos, sin);
+    }
+    obj += "f 1 2 3 4 5 6\n";
+    let scene = load("fan", &obj, "").1.unwrap().scene;
+    assert_eq!(scene.len(), 1);
+
+    // The whole hexagon is covered, and nothing outside it.
+    let inradius = 3f64.sqrt() / 2.0;
+    for i in 0..36 {
+        let (sin, cos) = (i as f64 * std::f64::consts::PI / 18.0).sin_cos();
+        for r in [0.0, 0.3, 0.6, 0.99 * inradius] {
+            assert!(hit(&scene, r * cos, r * sin).is_some(), "{} at {}", r, i);
+        }
+        assert!(
+            hit(&scene, 1.01 * cos, 1.01 * sin).is_none(),
+            "outside at {}",
+            i
+        );
+    }
+}
+
+#[test]
+fn mtl_materials_are_mapped() {
+    let mtl = "\
+newmtl red
+Kd 0.8 0.1 0.1
+newmtl lamp
+Kd 0 0 0
+Ke 4 3 2
+";
+    let obj = "\
+mtllib model.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 2 0 0
+v 3 0 0
+v 2 1 0
+v 4 0 0
+v 5 0 0
+v 4 1 0
+usemtl red
+f 1 2 3
+usemtl lamp
+f 4 5 6
+usemtl missing
+f 7 8 9
+";
+    let (path, loaded) = load("mtl", obj, mtl);
+    let Obj {
+        scene,
+        warnings,
+        files,
+    } = loaded.unwrap();
+    assert_eq!(files, [path.clone(), path.with_extension("mtl")]);
+    assert_eq!(scene.len(), 3);
+
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut albedo = |c: &Collision| {
+        let wo = Vec3::new(0.0, 0.0, 1.0);
+        c.material.sample(c, wo, &mut rng).map(|s| s.weight)
+    };
+
+    let red = hit(&scene, 0.25, 0.25).unwrap();
+    assert_eq!(albedo(&red), Some(Colour::new(0.8, 0.1, 0.1)));
+    assert_eq!(red.emitted(), Colour::BLACK);
+
+    let lamp = hit(&scene, 2.25, 0.25).unwrap();
+    assert_eq!(lamp.emitted(), Colour::new(4.0, 3.0, 2.0));
+
+    // Materials missing from the library fall back to grey, with a warning.
+    let missing = hit(&scene, 4.25, 0.25).unwrap();
+    assert_eq!(albedo(&missing), Some(Colour::new(0.5, 0.5, 0.5)));
+    assert_eq!(
+        warnings,
+        [Warning {
+            file: path.clone(),
+            line: 15,
+            message: "unknown material `missing`, using the default".to_string(),
+        }]
+    );
+    assert_eq!(
+        warnings[0].to_string(),
+        format!(
+            "{}:15: unknown material `missing`, using the default",
+            path.display()
+        )
+    );
+}
+
+#[test]
+fn errors_name_the_file_and_line() {
+    let (path, scene) = load("malformed", "v 0 0 0\n# comment\nv 1 x 0\n", "");
+    match scene {
+        Err(e) => assert_eq!(
+            e.to_string(),
+            format!("{}:3: invalid number `x`", path.display())
+        ),
+        Ok(_) => panic!("loaded a malformed file"),
+    }
+
+    let (path, scene) = load("short-face", "v 0 0 0\nv 1 0 0\nf 1 2\n", "");
+    match scene {
+        Err(e) => assert_eq!(
+            e.to_string(),
+            format!(
+                "{}:3: face needs at least 3 vertices, found 2",
+                path.display()
+            )
+        ),
+        Ok(_) => panic!("loaded a malformed file"),
+    }
+}
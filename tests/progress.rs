@@ -0,0 +1,93 @@
+mod common;
+
+use std::{
+    ops::ControlFlow,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use rez::{CancelToken, Progress, Raytracer};
+
+use common::random_raytracer;
+
+fn raytracer() -> Raytracer {
+    random_raytracer(3, 30, 20, 6)
+}
+
+#[derive(Default)]
+struct Counts {
+    started: AtomicU64,
+    pixels: AtomicU64,
+    samples: AtomicU64,
+    finished: AtomicU64,
+    /// Cancelled once this many pixels are done, if set.
+    cancel: Option<(u64, CancelToken)>,
+}
+
+impl Progress for Counts {
+    fn started(&self, pixels: u64) {
+        self.started.fetch_add(pixels, Ordering::SeqCst);
+    }
+
+    fn advanced(&self, pixels: u64, samples: u64) {
+        let done = self.pixels.fetch_add(pixels, Ordering::SeqCst) + pixels;
+        self.samples.fetch_add(samples, Ordering::SeqCst);
+        if let Some((after, token)) = &self.cancel {
+            if done >= *after {
+                token.cancel();
+            }
+        }
+    }
+
+    fn finished(&self) {
+        self.finished.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn progress_is_reported() {
+    let mut r = raytracer();
+    let counts = Arc::new(Counts::default());
+    r.progress = counts.clone();
+    r.render_progressive(4, |_| ControlFlow::Continue(()));
+
+    // Two passes over 600 pixels, of 4 samples then 2.
+    assert_eq!(counts.started.load(Ordering::SeqCst), 1200);
+    assert_eq!(counts.pixels.load(Ordering::SeqCst), 1200);
+    assert_eq!(counts.samples.load(Ordering::SeqCst), 600 * 6);
+    assert_eq!(counts.finished.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn cancelling_keeps_the_partial_image() {
+    let mut r = raytracer();
+    r.tile_size = 4;
+    let expected = r.render_linear();
+
+    r.progress = Arc::new(Counts {
+        cancel: Some((100, r.cancel.clone())),
+        ..Counts::default()
+    });
+    let image = r.render_progressive(6, |_| panic!("a cancelled pass isn't finished"));
+    assert_eq!(image.samples(), 0);
+    let counts = image.sample_counts();
+    assert!(counts.contains(&6));
+    assert!(counts.contains(&0));
+
+    // Carrying on from the partial image fills in the rest exactly.
+    let mut r = raytracer();
+    r.tile_size = 4;
+    let finished = r.render_from(image, 6, |_| ControlFlow::Continue(()));
+    assert_eq!(finished.linear(), expected);
+}
+
+#[test]
+fn cancelled_before_starting() {
+    let r = raytracer();
+    r.cancel.cancel();
+    let image = r.render_progressive(6, |_| ControlFlow::Continue(()));
+    assert_eq!(image.samples(), 0);
+    assert!(image.sample_counts().iter().all(|&n| n == 0));
+}
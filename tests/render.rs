@@ -0,0 +1,347 @@
+mod common;
+
+use std::{ops::ControlFlow, sync::Arc};
+
+use rand::RngCore;
+use rayon::ThreadPoolBuilder;
+
+use rez::{
+    Adaptive, Bvh, Camera, Collider, Colour, DiffuseLight, Integrator, Lambertian, Lights,
+    Material, Metal, PathTracer, Ray, Raytracer, Scene, Sphere, Triangle, TriangleMesh, Vec3,
+};
+
+use common::random_raytracer;
+
+fn raytracer() -> Raytracer {
+    random_raytracer(3, 24, 16, 4)
+}
+
+fn render_with_threads(r: &Raytracer, threads: usize) -> Vec<Colour> {
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap()
+        .install(|| r.render_linear())
+}
+
+#[test]
+fn same_seed_renders_identically() {
+    let mut r = raytracer();
+    r.seed = 42;
+    let expected = render_with_threads(&r, 1);
+
+    assert_eq!(render_with_threads(&r, 1), expected);
+    assert_eq!(render_with_threads(&r, 4), expected);
+    r.shuffle = true;
+    assert_eq!(render_with_threads(&r, 3), expected);
+}
+
+#[test]
+fn different_seeds_render_differently() {
+    let mut r = raytracer();
+    r.seed = 1;
+    let first = render_with_threads(&r, 2);
+    r.seed = 2;
+    assert_ne!(render_with_threads(&r, 2), first);
+}
+
+#[test]
+fn progressive_passes_add_up_to_one_render() {
+    let mut r = raytracer();
+    r.samples_per_pixel = 8;
+    let expected = r.render_linear();
+
+    let mut seen = Vec::new();
+    let image = r.render_progressive(3, |image| {
+        seen.push(image.samples());
+        ControlFlow::Continue(())
+    });
+    assert_eq!(seen, [3, 6, 8]);
+    assert_eq!(image.samples(), 8);
+    assert_eq!(image.linear(), expected);
+    assert_eq!(image.resolve(&r.display), r.render());
+}
+
+#[test]
+fn progressive_render_stops_when_asked() {
+    let mut r = raytracer();
+    r.samples_per_pixel = 100;
+    let mut passes = 0;
+    let image = r.render_progressive(2, |_| {
+        passes += 1;
+        if passes == 2 {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+    assert_eq!(passes, 2);
+    assert_eq!(image.samples(), 4);
+
+    r.samples_per_pixel = 4;
+    assert_eq!(image.linear(), r.render_linear());
+}
+
+#[test]
+fn adaptive_sampling_spends_samples_on_noise() {
+    let mut r = raytracer();
+    r.samples_per_pixel = 64;
+    let full = r.render_linear();
+
+    r.adaptive = Some(Adaptive {
+        threshold: 0.02,
+        min_samples: 4,
+    });
+    let image = r.render_progressive(64, |_| ControlFlow::Continue(()));
+    let counts = image.sample_counts();
+    // The sky is smooth, so stops as soon as it can, while the scene's edges and blurred
+    // foreground need more.
+    assert_eq!(counts[0], 4);
+    assert!(counts.iter().all(|&n| (4..=64).contains(&n)));
+    assert!(counts.contains(&64));
+
+    // Pixels which never settled got the same samples as without adaptive sampling.
+    let linear = image.linear();
+    for (i, &n) in counts.iter().enumerate() {
+        if n == 64 {
+            assert_eq!(linear[i], full[i]);
+        }
+    }
+
+    // Where each pixel stops doesn't depend on the passes.
+    assert_eq!(
+        r.render_progressive(5, |_| ControlFlow::Continue(())),
+        image
+    );
+
+    let map = image.sample_map();
+    assert_eq!(map[0], Colour::new(0.0, 0.125, 0.875));
+    let busiest = counts.iter().position(|&n| n == 64).unwrap();
+    assert_eq!(map[busiest], Colour::new(1.0, 0.0, 0.0));
+}
+
+type Shared = Arc<dyn Material + Send + Sync>;
+
+fn quad(o: Vec3, u: Vec3, v: Vec3, material: &Shared) -> TriangleMesh<Shared> {
+    TriangleMesh::new(
+        vec![o, o + u, o + u + v, o + v],
+        vec![[0, 1, 2], [0, 2, 3]],
+        Arc::new(material.clone()),
+    )
+    .unwrap()
+}
+
+/// A closed grey room with a ball in it, lit by one light of each shape.
+fn room(samples: u32, sample_lights: bool, seed: u64) -> Raytracer {
+    let cam = Camera::builder()
+        .origin(Vec3::new(5.0, 5.0, 0.5))
+        .target(Vec3::new(5.0, 5.0, 10.0))
+        .vup(Vec3::new(0.0, 1.0, 0.0))
+        .v_fov(f64::to_radians(90.0))
+        .aspect_ratio(1.0)
+        .aperture(0.0)
+        .focus_dist(10.0)
+        .build()
+        .unwrap();
+
+    let grey: Shared = Arc::new(Lambertian::new(Colour::new(0.6, 0.6, 0.6)));
+    let glow: Shared = Arc::new(DiffuseLight::new(Colour::new(20.0, 20.0, 20.0)));
+    let (x, y, z) = (
+        Vec3::new(10.0, 0.0, 0.0),
+        Vec3::new(0.0, 10.0, 0.0),
+        Vec3::new(0.0, 0.0, 10.0),
+    );
+
+    let mut scene: Scene = vec![
+        Box::new(quad(Vec3::ZERO, x, z, &grey)),
+        Box::new(quad(y, x, z, &grey)),
+        Box::new(quad(Vec3::ZERO, x, y, &grey)),
+        Box::new(quad(z, x, y, &grey)),
+        Box::new(quad(Vec3::ZERO, y, z, &grey)),
+        Box::new(quad(x, y, z, &grey)),
+        Box::new(Sphere::new(
+            Vec3::new(6.0, 2.0, 6.0),
+            2.0,
+            Arc::new(grey.clone()),
+        )),
+    ];
+    let lights: Lights = vec![
+        Arc::new(quad(
+            Vec3::new(4.0, 9.99, 4.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            &glow,
+        )),
+        Arc::new(Sphere::new(
+            Vec3::new(2.0, 6.0, 7.0),
+            0.5,
+            Arc::new(glow.clone()),
+        )),
+        Arc::new(Triangle::new(
+            [
+                Vec3::new(9.99, 3.0, 2.0),
+                Vec3::new(9.99, 3.0, 4.0),
+                Vec3::new(9.99, 5.0, 3.0),
+            ],
+            Arc::new(glow.clone()),
+        )),
+    ];
+    scene.extend(
+        lights
+            .iter()
+            .map(|l| -> Box<dyn Collider + Send + Sync> { Box::new(l.clone()) }),
+    );
+
+    let mut r = Raytracer::new(Arc::new(Bvh::new(scene)), cam, 6, 6, samples, 5);
+    if sample_lights {
+        r.lights = lights;
+    }
+    r.background = Arc::new(Colour::BLACK);
+    r.seed = seed;
+    r
+}
+
+/// The mean brightness of images rendered with several seeds, and its standard error.
+fn mean_brightness(raytracer: impl Fn(u64) -> Raytracer) -> (f64, f64) {
+    const RUNS: u64 = 8;
+    let means: Vec<f64> = (0..RUNS)
+        .map(|seed| {
+            let pixels = raytracer(seed).render_linear();
+            let total: f64 = pixels.iter().map(|c| c.r + c.g + c.b).sum();
+            total / (3 * pixels.len()) as f64
+        })
+        .collect();
+
+    let mean = means.iter().sum::<f64>() / RUNS as f64;
+    let variance = means.iter().map(|m| (m - mean).powi(2)).sum::<f64>() / (RUNS - 1) as f64;
+    (mean, (variance / RUNS as f64).sqrt())
+}
+
+/// Path tracing at its simplest: follow the material's sampling at every bounce, and only count
+/// light the path happens to hit. Knows nothing of the scene's lights, so is an independent
+/// check on the path tracer's light sampling and Russian roulette.
+struct Naive;
+
+impl Integrator for Naive {
+    fn radiance(&self, rt: &Raytracer, mut ray: Ray, rng: &mut dyn RngCore) -> Colour {
+        let mut radiance = Colour::BLACK;
+        let mut throughput = Colour::WHITE;
+        for _ in 0..rt.bounce_depth {
+            let c = match rt.scene.collide(ray, (0.001, f64::INFINITY)) {
+                Some(c) => c,
+                None => return radiance + throughput.scale(rt.background.radiance(ray.dir)),
+            };
+            radiance += throughput.scale(c.emitted());
+            match c.material.sample(&c, -ray.dir, rng) {
+                Some(sample) => {
+                    throughput = throughput.scale(sample.weight);
+                    ray = Ray::new(c.point, sample.dir);
+                }
+                None => break,
+            }
+        }
+        radiance
+    }
+}
+
+/// Check two estimates of an image's brightness agree, to within their noise.
+fn assert_agree(name: &str, (a, a_error): (f64, f64), (b, b_error): (f64, f64)) {
+    let error = a_error.hypot(b_error);
+    assert!(
+        (a - b).abs() < 4.0 * error,
+        "naive {} ± {}, {} {} ± {}",
+        a,
+        a_error,
+        name,
+        b,
+        b_error
+    );
+}
+
+#[test]
+fn light_sampling_matches_naive_path_tracing() {
+    let naive = mean_brightness(|seed| {
+        let mut r = room(1000, false, seed);
+        r.integrator = Arc::new(Naive);
+        r
+    });
+    let sampled = mean_brightness(|seed| {
+        let mut r = room(250, true, seed);
+        r.integrator = Arc::new(PathTracer::new(u32::MAX));
+        r
+    });
+    assert_agree("with light sampling", naive, sampled);
+}
+
+#[test]
+fn roulette_matches_naive_path_tracing() {
+    let with = |integrator: Arc<dyn Integrator + Send + Sync>, samples| {
+        move |seed| {
+            let mut r = room(samples, true, seed);
+            // A shiny ball keeps some paths bright for longer than others.
+            let mut scene: Scene = vec![Box::new(r.scene.clone())];
+            scene.push(Box::new(Sphere::new(
+                Vec3::new(3.0, 1.5, 3.0),
+                1.5,
+                Arc::new(Metal::new(Colour::new(0.9, 0.8, 0.7), 0.2)),
+            )));
+            r.scene = Arc::new(scene);
+            r.bounce_depth = 12;
+            r.integrator = integrator.clone();
+            r
+        }
+    };
+    let naive = mean_brightness(with(Arc::new(Naive), 1000));
+    let roulette = mean_brightness(with(Arc::new(PathTracer::new(2)), 250));
+    assert_agree("with Russian roulette", naive, roulette);
+}
+
+#[test]
+fn lit_plane_matches_the_analytic_value() {
+    // A grey plane under a glowing ball, seen from the side. Light only reaches the camera by
+    // one bounce off the plane, and `Lambertian` scatters evenly over the hemisphere, so the
+    // plane reflects `albedo × radiance × (1 - cos α)` for a ball of angular radius α.
+    let cam = Camera::builder()
+        .origin(Vec3::new(0.0, 5.0, 5.0))
+        .target(Vec3::ZERO)
+        .vup(Vec3::new(0.0, 1.0, 0.0))
+        .v_fov(f64::to_radians(0.01))
+        .aspect_ratio(1.0)
+        .aperture(0.0)
+        .focus_dist(10.0)
+        .build()
+        .unwrap();
+    let grey: Shared = Arc::new(Lambertian::new(Colour::new(0.5, 0.5, 0.5)));
+    let glow: Shared = Arc::new(DiffuseLight::new(Colour::new(16.0, 16.0, 16.0)));
+    let ball = Arc::new(Sphere::new(Vec3::new(0.0, 2.0, 0.0), 0.5, Arc::new(glow)));
+    let scene: Scene = vec![
+        Box::new(quad(
+            Vec3::new(-100.0, 0.0, -100.0),
+            Vec3::new(0.0, 0.0, 200.0),
+            Vec3::new(200.0, 0.0, 0.0),
+            &grey,
+        )),
+        Box::new(ball.clone()),
+    ];
+
+    let cos_alpha = (1.0 - (0.5f64 / 2.0).powi(2)).sqrt();
+    let expected = 0.5 * 16.0 * (1.0 - cos_alpha);
+
+    let mut r = Raytracer::new(Arc::new(scene), cam, 3, 3, 10_000, 5);
+    r.background = Arc::new(Colour::BLACK);
+    r.lights = vec![ball];
+    for integrator in [
+        Arc::new(Naive) as Arc<dyn Integrator + Send + Sync>,
+        Arc::new(PathTracer::default()),
+    ] {
+        r.integrator = integrator;
+        let pixels = r.render_linear();
+        let mean = pixels.iter().map(|c| c.r + c.g + c.b).sum::<f64>() / (3 * pixels.len()) as f64;
+        assert!(
+            (mean - expected).abs() < 0.1 * expected,
+            "expected {}, got {}",
+            expected,
+            mean
+        );
+    }
+}
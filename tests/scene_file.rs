@@ -0,0 +1,198 @@
+use std::{env, fs, path::PathBuf, process};
+
+use rez::load_scene;
+
+const SCENE: &str = r#"
+[render]
+width = 20
+height = 10
+
+[camera]
+origin = [0, 0, 0]
+target = [0, 0, -1]
+v_fov = 40
+
+[materials.grey]
+type = "lambertian"
+albedo = [0.5, 0.5, 0.5]
+
+[materials.steel]
+type = "metal"
+albedo = [0.8, 0.8, 0.8]
+fuzz = 0.1
+
+[[objects]]
+type = "sphere"
+centre = [0, 0, -2]
+radius = 0.5
+material = "grey"
+
+[[objects]]
+type = "triangle"
+vertices = [
+    [-1, -1, -3],
+    [1, -1, -3],
+    [0, 1, -3],
+]
+material = "steel"
+"#;
+
+/// Load `SCENE` with `from` replaced by `to`, returning the file it was in and the error.
+fn error(name: &str, from: &str, to: &str) -> (PathBuf, String) {
+    assert!(SCENE.contains(from), "`{}` isn't in the scene", from);
+    let path = env::temp_dir().join(format!("rez-scene-{}-{}.toml", process::id(), name));
+    fs::write(&path, SCENE.replacen(from, to, 1)).unwrap();
+    let loaded = load_scene(&path);
+    fs::remove_file(&path).unwrap();
+    match loaded {
+        Err(e) => (path.clone(), e.to_string()),
+        Ok(_) => panic!("loaded a scene with `{}` in place of `{}`", to, from),
+    }
+}
+
+#[test]
+fn valid_scene_loads() {
+    let path = env::temp_dir().join(format!("rez-scene-{}-valid.toml", process::id()));
+    fs::write(&path, SCENE).unwrap();
+    let loaded = load_scene(&path);
+    fs::remove_file(&path).unwrap();
+
+    let (r, warnings) = loaded.unwrap();
+    assert_eq!((r.width, r.height), (20, 10));
+    assert!(warnings.is_empty());
+    assert!(load_scene("scenes/cornell.toml").is_ok());
+}
+
+#[test]
+fn unknown_keys_are_named() {
+    let (path, message) = error("unknown", "height = 10", "height = 10\nsamples = 4");
+    assert!(
+        message.starts_with(&format!(
+            "{}:5: `render.samples`: unknown field `samples`, expected one of",
+            path.display()
+        )),
+        "{}",
+        message
+    );
+
+    let (path, message) = error("unknown-object", "radius = 0.5", "radius = 0.5\nradios = 1");
+    assert!(
+        message.starts_with(&format!("{}:", path.display())) && message.contains("`radios`"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn wrong_types_are_named() {
+    let (path, message) = error("type", "width = 20", "width = \"wide\"");
+    assert_eq!(
+        message,
+        format!(
+            "{}:3: `render.width`: invalid type: string \"wide\", expected u32",
+            path.display()
+        )
+    );
+
+    let (path, message) = error("type-camera", "v_fov = 40", "v_fov = [40]");
+    assert_eq!(
+        message,
+        format!(
+            "{}:9: `camera.v_fov`: invalid type: sequence, expected f64",
+            path.display()
+        )
+    );
+
+    // Tagged tables are only named as a whole, numbered if they are in an array.
+    let (path, message) = error("type-object", "radius = 0.5", "radius = \"big\"");
+    assert_eq!(
+        message,
+        format!(
+            "{}:20: `objects[0]`: invalid type: string \"big\", expected f64",
+            path.display()
+        )
+    );
+}
+
+#[test]
+fn out_of_range_values_are_named() {
+    let (path, message) = error("range", "width = 20", "width = 1");
+    assert_eq!(
+        message,
+        format!("{}: `render.width` must be at least 2", path.display())
+    );
+
+    let (path, message) = error("range-fuzz", "fuzz = 0.1", "fuzz = 1.5");
+    assert_eq!(
+        message,
+        format!(
+            "{}: `materials.steel.fuzz` must be between 0 and 1",
+            path.display()
+        )
+    );
+
+    let (path, message) = error("range-albedo", "[0.5, 0.5, 0.5]", "[0.5, -0.5, 0.5]");
+    assert_eq!(
+        message,
+        format!(
+            "{}: `materials.grey.albedo` must not have negative or infinite components",
+            path.display()
+        )
+    );
+}
+
+#[test]
+fn missing_materials_are_named() {
+    let (path, message) = error("material", "material = \"steel\"", "material = \"gold\"");
+    assert_eq!(
+        message,
+        format!(
+            "{}: `objects[1].material` names unknown material `gold`",
+            path.display()
+        )
+    );
+}
+
+#[test]
+fn scene_ids_cover_every_file_read() {
+    let dir = env::temp_dir().join(format!("rez-scene-{}-files", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let scene = dir.join("scene.toml");
+    let obj = "mtllib model.mtl\nv 0 0 -3\nv 1 0 -3\nv 0 1 -3\nusemtl red\nf 1 2 3\n";
+    fs::write(
+        &scene,
+        format!(
+            "{}\n[[objects]]\ntype = \"obj\"\npath = \"model.obj\"\n",
+            SCENE
+        ),
+    )
+    .unwrap();
+    fs::write(dir.join("model.obj"), obj).unwrap();
+    fs::write(dir.join("model.mtl"), "newmtl red\nKd 1 0 0\n").unwrap();
+
+    let load = || load_scene(&scene).unwrap().0.scene_id.unwrap();
+    let original = load();
+    assert_eq!(load(), original);
+
+    fs::write(dir.join("model.mtl"), "newmtl red\nKd 0 1 0\n").unwrap();
+    let recoloured = load();
+    assert_ne!(recoloured, original);
+
+    fs::write(
+        dir.join("model.obj"),
+        obj.replace("usemtl red", "usemtl blue"),
+    )
+    .unwrap();
+    let (r, warnings) = load_scene(&scene).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(![original, recoloured].contains(&r.scene_id.unwrap()));
+    // Warnings from the files the scene uses are passed on.
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(
+        warnings[0].to_string(),
+        format!(
+            "{}:5: unknown material `blue`, using the default",
+            dir.join("model.obj").display()
+        )
+    );
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use std::sync::Arc;
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use rez::{
+    encode_png, Checker, Collider, Colour, ImageTexture, Lambertian, Marble, Perlin, Ray, Sphere,
+    Texture, Vec3, WrapMode,
+};
+
+use common::{close, EPSILON};
+
+#[test]
+fn sphere_uv_wraps_around_the_y_axis() {
+    let sphere = Sphere::new(Vec3::ZERO, 2.0, Arc::new(Lambertian::new(Colour::WHITE)));
+    let uv_from = |dir: Vec3| {
+        let ray = Ray::new(dir * 5.0, -dir);
+        sphere.collide(ray, (0.001, f64::INFINITY)).unwrap().uv
+    };
+
+    let cases = [
+        (Vec3::new(1.0, 0.0, 0.0), (0.5, 0.5)),
+        (Vec3::new(0.0, 0.0, 1.0), (0.25, 0.5)),
+        (Vec3::new(0.0, 0.0, -1.0), (0.75, 0.5)),
+        (Vec3::new(0.0, 1.0, 0.0), (0.5, 1.0)),
+        (Vec3::new(0.0, -1.0, 0.0), (0.5, 0.0)),
+    ];
+    for (dir, (u, v)) in cases {
+        let uv = uv_from(dir);
+        assert!((uv.0 - u).abs() < 1e-9, "u at {:?} was {}", dir, uv.0);
+        assert!((uv.1 - v).abs() < 1e-9, "v at {:?} was {}", dir, uv.1);
+    }
+}
+
+#[test]
+fn checker_alternates_between_cells() {
+    let checker = Checker::new(1.0, Colour::WHITE, Colour::BLACK);
+    let at = |x, y, z| checker.value((0.0, 0.0), Vec3::new(x, y, z));
+
+    assert_eq!(at(0.5, 0.5, 0.5), Colour::WHITE);
+    assert_eq!(at(1.5, 0.5, 0.5), Colour::BLACK);
+    assert_eq!(at(1.5, 1.5, 0.5), Colour::WHITE);
+    assert_eq!(at(-0.5, 0.5, 0.5), Colour::BLACK);
+    assert_eq!(at(-0.5, -0.5, -0.5), Colour::BLACK);
+}
+
+#[test]
+fn noise_is_smooth_and_bounded() {
+    let perlin = Perlin::new(&mut StdRng::seed_from_u64(1));
+    // Gradient noise is zero on its lattice.
+    assert_eq!(perlin.noise(Vec3::new(3.0, -2.0, 7.0)), 0.0);
+
+    let p = Vec3::new(0.3, 1.7, -2.2);
+    let step = Vec3::new(1e-6, 0.0, 0.0);
+    assert!((perlin.noise(p) - perlin.noise(p + step)).abs() < 1e-5);
+
+    let marble = Marble::new(perlin, 4.0);
+    for i in 0..100 {
+        let c = marble.value(
+            (0.0, 0.0),
+            Vec3::new(i as f64 * 0.37, 0.1, i as f64 * -0.21),
+        );
+        assert!((0.0..=1.0).contains(&c.r), "marble gave {:?}", c);
+    }
+}
+
+/// A 2 × 2 image: red and green on the top row, blue and white below.
+fn image() -> ImageTexture {
+    ImageTexture::new(
+        2,
+        2,
+        vec![
+            Colour::new(1.0, 0.0, 0.0),
+            Colour::new(0.0, 1.0, 0.0),
+            Colour::new(0.0, 0.0, 1.0),
+            Colour::WHITE,
+        ],
+    )
+}
+
+#[test]
+fn image_is_bilinearly_filtered() {
+    let image = image().wrapped(WrapMode::Clamp);
+    let at = |u, v| image.value((u, v), Vec3::ZERO);
+
+    assert!(close(at(0.25, 0.75), Colour::new(1.0, 0.0, 0.0), EPSILON));
+    assert!(close(at(0.75, 0.25), Colour::WHITE, EPSILON));
+    assert!(close(at(0.5, 0.75), Colour::new(0.5, 0.5, 0.0), EPSILON));
+    assert!(close(at(0.5, 0.5), Colour::new(0.5, 0.5, 0.5), EPSILON));
+}
+
+#[test]
+fn image_wrap_modes() {
+    let at = |wrap, u| image().wrapped(wrap).value((u, 0.75), Vec3::ZERO);
+    let (red, green) = (Colour::new(1.0, 0.0, 0.0), Colour::new(0.0, 1.0, 0.0));
+
+    // Half a pixel past the right edge.
+    assert!(close(at(WrapMode::Clamp, 1.25), green, EPSILON));
+    assert!(close(at(WrapMode::Repeat, 1.25), red, EPSILON));
+    assert!(close(at(WrapMode::Mirror, 1.25), green, EPSILON));
+    // Exactly on the edge, between the last pixel and whatever lies beyond.
+    assert!(close(
+        at(WrapMode::Repeat, 1.0),
+        Colour::new(0.5, 0.5, 0.0),
+        EPSILON
+    ));
+    assert!(close(at(WrapMode::Mirror, 1.0), green, EPSILON));
+}
+
+#[test]
+fn lambertian_takes_albedo_from_its_texture() {
+    let checker = Checker::new(1.0, Colour::new(0.8, 0.2, 0.2), Colour::new(0.2, 0.2, 0.8));
+    let sphere = Sphere::new(
+        Vec3::new(0.5, 0.5, -10.0),
+        0.25,
+        Arc::new(Lambertian::textured(checker.clone())),
+    );
+    let ray = Ray::new(Vec3::new(0.5, 0.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+    let col = sphere.collide(ray, (0.001, f64::INFINITY)).unwrap();
+
+    let mut rng = StdRng::seed_from_u64(3);
+    let sample = col.material.sample(&col, -ray.dir, &mut rng).unwrap();
+    assert_eq!(sample.weight, checker.value(col.uv, col.point));
+}
+
+#[test]
+fn image_files_are_decoded_to_linear() {
+    let path = std::env::temp_dir().join(format!("rez-texture-{}.png", std::process::id()));
+    let pixels = [Colour::WHITE, Colour::new(0.5, 0.0, 1.0)];
+    let mut file = std::fs::File::create(&path).unwrap();
+    encode_png(&pixels, 2, 1, &mut file).unwrap();
+    drop(file);
+
+    let texture = ImageTexture::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(close(
+        texture.value((0.25, 0.5), Vec3::ZERO),
+        Colour::WHITE,
+        EPSILON
+    ));
+    // 0.5 is stored as 128 / 255, which is about 0.216 once the sRGB curve is undone.
+    let c = texture.value((0.75, 0.5), Vec3::ZERO);
+    assert!((c.r - 0.2158605).abs() < 1e-6, "decoded {:?}", c);
+    assert_eq!((c.g, c.b), (0.0, 1.0));
+}
+
+#[test]
+#[should_panic(expected = "image texture must not be empty")]
+fn empty_textures_are_refused() {
+    ImageTexture::new(0, 0, vec![]);
+}
+
+#[test]
+fn empty_image_files_are_an_error() {
+    let path = std::env::temp_dir().join(format!("rez-texture-{}-empty.hdr", std::process::id()));
+    std::fs::write(&path, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 0 +X 0\n").unwrap();
+    let texture = ImageTexture::open(&path);
+    std::fs::remove_file(&path).unwrap();
+    match texture {
+        Err(e) => assert!(e.to_string().contains("dimension"), "{}", e),
+        Ok(_) => panic!("opened an empty texture"),
+    }
+}
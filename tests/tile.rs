@@ -0,0 +1,99 @@
+mod common;
+
+use std::ops::ControlFlow;
+
+use rez::{Colour, Raytracer, Region, TileOrder};
+
+use common::random_raytracer;
+
+const ORDERS: [TileOrder; 3] = [TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert];
+
+#[test]
+fn tiles_cover_every_pixel_once() {
+    let area = Region::new(5, 3, 37, 23);
+    for order in ORDERS {
+        let tiles = order.tiles(area, 8);
+        assert_eq!(tiles.len(), 5 * 3);
+        let mut seen = vec![0; 64 * 64];
+        for tile in &tiles {
+            assert!(tile.width <= 8 && tile.height <= 8);
+            for (x, y) in tile.pixels() {
+                seen[y as usize * 64 + x as usize] += 1;
+            }
+        }
+        for (x, y) in Region::new(0, 0, 64, 64).pixels() {
+            let expected = area.pixels().any(|p| p == (x, y)) as i32;
+            assert_eq!(seen[y as usize * 64 + x as usize], expected, "{:?}", order);
+        }
+    }
+}
+
+#[test]
+fn tile_orders() {
+    let area = Region::new(0, 0, 40, 40);
+    let corner = |tiles: Vec<Region>| -> Vec<(u32, u32)> {
+        tiles.iter().map(|t| (t.x / 10, t.y / 10)).collect()
+    };
+
+    let scanline = corner(TileOrder::Scanline.tiles(area, 10));
+    assert_eq!(scanline[..5], [(0, 0), (1, 0), (2, 0), (3, 0), (0, 1)]);
+
+    let spiral = corner(TileOrder::Spiral.tiles(area, 10));
+    assert_eq!(spiral[..5], [(2, 2), (3, 2), (3, 3), (2, 3), (1, 3)]);
+
+    // Each tile along the Hilbert curve is next to the one before.
+    let hilbert = corner(TileOrder::Hilbert.tiles(area, 10));
+    assert_eq!(hilbert[0], (0, 0));
+    for pair in hilbert.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        assert_eq!(x0.abs_diff(x1) + y0.abs_diff(y1), 1, "{:?}", pair);
+    }
+}
+
+fn raytracer() -> Raytracer {
+    random_raytracer(3, 30, 20, 4)
+}
+
+#[test]
+fn tiling_doesnt_change_the_image() {
+    let mut r = raytracer();
+    let expected = r.render_linear();
+    for order in ORDERS {
+        for size in [1, 7, 64] {
+            r.tile_order = order;
+            r.tile_size = size;
+            assert_eq!(r.render_linear(), expected, "{:?} tiles of {}", order, size);
+        }
+    }
+}
+
+#[test]
+fn regions_paste_back_into_the_image() {
+    let mut r = raytracer();
+    let full = r.render_linear();
+
+    let region = Region::new(7, 4, 12, 9);
+    r.region = Some(region);
+    let image = r.render_progressive(4, |_| ControlFlow::Continue(()));
+    assert_eq!((image.width(), image.height()), (12, 9));
+    let crop = image.linear();
+    for ((x, y), colour) in region.pixels().zip(&crop) {
+        assert_eq!(*colour, full[y as usize * 30 + x as usize]);
+    }
+
+    let mut pasted = vec![Colour::BLACK; full.len()];
+    Region::new(0, 0, 30, 20).paste(&mut pasted, 30, &full);
+    assert_eq!(pasted, full);
+    let mut patched = full.clone();
+    region.paste(&mut patched, 30, &vec![Colour::WHITE; crop.len()]);
+    let changed = patched.iter().zip(&full).filter(|(a, b)| a != b).count();
+    assert_eq!(changed, 12 * 9);
+}
+
+#[test]
+fn regions_must_fit() {
+    assert!(Region::new(0, 0, 30, 20).fits(30, 20));
+    assert!(!Region::new(1, 0, 30, 20).fits(30, 20));
+    assert!(!Region::new(0, 0, 0, 20).fits(30, 20));
+    assert!(!Region::new(u32::MAX, 0, 2, 2).fits(30, 20));
+}
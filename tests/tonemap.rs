@@ -0,0 +1,87 @@
+mod common;
+
+use rez::{Colour, DisplayTransform, ToneMap, Transfer};
+
+use common::close;
+
+#[test]
+fn srgb_curve() {
+    let srgb = Transfer::Srgb;
+    assert_eq!(srgb.encode(0.0), 0.0);
+    assert!(close(srgb.encode(1.0), 1.0, 1e-4));
+    assert!(close(srgb.encode(0.5), 0.7354, 1e-4));
+    assert!(close(srgb.encode(0.001), 0.01292, 1e-4));
+    // The two pieces meet.
+    let knee = 0.0031308;
+    assert!(close(
+        srgb.encode(knee - 1e-9),
+        srgb.encode(knee + 1e-9),
+        1e-4
+    ));
+    assert_eq!(srgb.encode(-1.0), 0.0);
+
+    assert!(close(Transfer::Gamma(2.0).encode(0.25), 0.5, 1e-4));
+}
+
+#[test]
+fn exposure_is_in_stops() {
+    let linear = |exposure: f64| DisplayTransform {
+        exposure,
+        tone_map: ToneMap::Clamp,
+        transfer: Transfer::Gamma(1.0),
+    };
+    let grey = Colour::new(0.25, 0.25, 0.25);
+    assert_eq!(linear(1.0).apply(grey), Colour::new(0.5, 0.5, 0.5));
+    assert_eq!(
+        linear(-2.0).apply(grey),
+        Colour::new(0.0625, 0.0625, 0.0625)
+    );
+}
+
+#[test]
+fn tone_maps_bring_highlights_into_range() {
+    let white = 4.0;
+    for tone_map in [
+        ToneMap::Reinhard,
+        ToneMap::ExtendedReinhard { white },
+        ToneMap::Aces,
+        ToneMap::Hable,
+    ] {
+        let curve = |v: f64| tone_map.apply(Colour::new(v, v, v)).g;
+        assert!(close(curve(0.0), 0.0, 1e-4), "{:?}", tone_map);
+        let mut last = 0.0;
+        for v in (1..=400).map(|i| i as f64 * 0.05) {
+            let mapped = curve(v);
+            assert!(mapped >= last, "{:?} falls at {}", tone_map, v);
+            assert!(mapped <= 1.0 + 1e-9 || v > white, "{:?} at {}", tone_map, v);
+            last = mapped;
+        }
+    }
+
+    assert!(close(ToneMap::Reinhard.apply(Colour::WHITE).r, 0.5, 1e-4));
+    let extended = ToneMap::ExtendedReinhard { white };
+    assert!(close(
+        extended.apply(Colour::new(white, white, white)).r,
+        1.0,
+        1e-4
+    ));
+    // Hable's curve is white at 11.2 after doubling.
+    assert!(close(
+        ToneMap::Hable.apply(Colour::new(5.6, 5.6, 5.6)).r,
+        1.0,
+        1e-4
+    ));
+    assert!(ToneMap::Aces.apply(Colour::new(1e6, 1e6, 1e6)).r <= 1.0);
+}
+
+#[test]
+fn reinhard_keeps_hue() {
+    let orange = Colour::new(6.0, 3.0, 0.0);
+    for tone_map in [ToneMap::Reinhard, ToneMap::ExtendedReinhard { white: 4.0 }] {
+        let mapped = tone_map.apply(orange);
+        assert!(close(mapped.r / mapped.g, 2.0, 1e-4));
+        assert_eq!(mapped.b, 0.0);
+        assert!(mapped.luminance() < 1.0);
+    }
+    assert_eq!(ToneMap::Reinhard.apply(Colour::BLACK), Colour::BLACK);
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use std::{f64::consts::PI, sync::Arc};
+
+use rez::{
+    Aabb, Bvh, Collider, Colour, Instance, Lambertian, Ray, Scene, Sphere, Transform, TriangleMesh,
+    Vec3,
+};
+
+use common::{close, EPSILON};
+
+fn placement() -> Transform {
+    Transform::translate(Vec3::new(1.0, -2.0, 3.0))
+        * Transform::rotate(Vec3::new(1.0, 2.0, 3.0), 0.7)
+        * Transform::scale(Vec3::new(2.0, 0.5, 3.0))
+}
+
+#[test]
+fn transforms_undo() {
+    let t = placement();
+    let p = Vec3::new(0.3, -4.0, 2.5);
+    assert!(close(t.inverse().point(t.point(p)), p, EPSILON));
+    assert!(close(t.point(t.inverse().point(p)), p, EPSILON));
+
+    let from_matrix = Transform::from_matrix(t.matrix()).unwrap();
+    assert!(close(from_matrix.inverse().point(t.point(p)), p, EPSILON));
+
+    let mut flat = t.matrix();
+    flat[2] = [0.0, 0.0, 0.0, 4.0];
+    assert!(Transform::from_matrix(flat).is_none());
+}
+
+#[test]
+fn transforms_compose_right_to_left() {
+    let rotate = Transform::rotate_z(PI / 2.0);
+    let x = Vec3::new(1.0, 0.0, 0.0);
+    assert!(close(rotate.point(x), Vec3::new(0.0, 1.0, 0.0), EPSILON));
+
+    let shift = Transform::translate(Vec3::new(5.0, 0.0, 0.0));
+    assert!(close(
+        (shift * rotate).point(x),
+        Vec3::new(5.0, 1.0, 0.0),
+        EPSILON
+    ));
+    assert!(close(
+        (rotate * shift).point(x),
+        Vec3::new(0.0, 6.0, 0.0),
+        EPSILON
+    ));
+    // Directions ignore translation.
+    assert!(close(
+        (shift * rotate).vector(x),
+        Vec3::new(0.0, 1.0, 0.0),
+        EPSILON
+    ));
+}
+
+#[test]
+fn instance_matches_the_object_it_stands_for() {
+    let material = Arc::new(Lambertian::new(Colour::WHITE));
+    let (centre, radius) = (Vec3::new(2.0, 1.0, -3.0), 1.5);
+    let sphere = Sphere::new(centre, radius, material.clone());
+    let instance = Instance::new(
+        Arc::new(Sphere::new(Vec3::ZERO, 1.0, material)),
+        Transform::translate(centre) * Transform::rotate_y(1.0) * Transform::uniform_scale(radius),
+    );
+
+    for offset in [
+        Vec3::ZERO,
+        Vec3::new(0.5, 0.3, 0.0),
+        Vec3::new(-1.0, 0.2, 0.4),
+    ] {
+        let origin = Vec3::new(0.0, 0.0, 5.0);
+        let ray = Ray::new(origin, (centre + offset - origin) * 0.3);
+        let expected = sphere.collide(ray, (0.001, f64::INFINITY)).unwrap();
+        let actual = instance.collide(ray, (0.001, f64::INFINITY)).unwrap();
+        assert!((expected.t - actual.t).abs() < 1e-9);
+        assert!(close(expected.point, actual.point, EPSILON));
+        assert!(close(expected.normal, actual.normal, EPSILON));
+        assert_eq!(expected.front, actual.front);
+    }
+
+    // Rotating the sphere's box leaves it bigger than it need be, but it must still hold the
+    // sphere.
+    let b = instance.bounding_box().unwrap();
+    let tight = sphere.bounding_box().unwrap();
+    for axis in 0..3 {
+        assert!(b.min[axis] <= tight.min[axis] + 1e-9 && b.max[axis] >= tight.max[axis] - 1e-9);
+    }
+}
+
+#[test]
+fn stretched_normals_stay_perpendicular() {
+    // An ellipsoid x²/4 + y² + z² = 1.
+    let ellipsoid = Instance::new(
+        Arc::new(Sphere::new(
+            Vec3::ZERO,
+            1.0,
+            Arc::new(Lambertian::new(Colour::WHITE)),
+        )),
+        Transform::scale(Vec3::new(2.0, 1.0, 1.0)),
+    );
+    let target = Vec3::new(2f64.sqrt(), 0.5f64.sqrt(), 0.0);
+    let gradient = Vec3::new(target.x / 4.0, target.y, 0.0).unit();
+    let ray = Ray::new(target + gradient * 3.0, -gradient);
+
+    let col = ellipsoid.collide(ray, (0.001, f64::INFINITY)).unwrap();
+    assert!(close(col.point, target, EPSILON));
+    assert!(close(col.normal, gradient, EPSILON));
+}
+
+#[test]
+fn rotated_bounds_grow() {
+    let cube = Aabb::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+    let b = Transform::rotate_z(PI / 4.0).aabb(cube);
+    let s = 2f64.sqrt();
+    assert!(close(b.min, Vec3::new(-s, -s, -1.0), EPSILON));
+    assert!(close(b.max, Vec3::new(s, s, 1.0), EPSILON));
+}
+
+#[test]
+fn instances_share_one_mesh() {
+    let mesh: Arc<dyn Collider + Send + Sync> = Arc::new(
+        TriangleMesh::new(
+            vec![
+                Vec3::new(-0.5, -0.5, 0.0),
+                Vec3::new(0.5, -0.5, 0.0),
+                Vec3::new(0.0, 0.5, 0.0),
+            ],
+            vec![[0, 1, 2]],
+            Arc::new(Lambertian::new(Colour::WHITE)),
+        )
+        .unwrap(),
+    );
+
+    let scene: Scene = (0..1000)
+        .map(|i| -> Box<dyn Collider + Send + Sync> {
+            let (x, y) = ((i % 40) as f64 * 2.0, (i / 40) as f64 * 2.0);
+            Box::new(Instance::new(
+                mesh.clone(),
+                Transform::translate(Vec3::new(x, y, -(i as f64))) * Transform::rotate_z(i as f64),
+            ))
+        })
+        .collect();
+    let bvh = Bvh::new(scene);
+    assert_eq!(Arc::strong_count(&mesh), 1001);
+
+    // Each copy is where it was put.
+    for i in [0, 17, 999] {
+        let (x, y) = ((i % 40) as f64 * 2.0, (i / 40) as f64 * 2.0);
+        let ray = Ray::new(Vec3::new(x, y, 10.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit = bvh.collide(ray, (0.001, f64::INFINITY)).unwrap();
+        assert!((hit.point.z + i as f64).abs() < 1e-9, "hit {}", hit.point);
+    }
+}